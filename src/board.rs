@@ -0,0 +1,444 @@
+// Replays a Go game record to find the position after any node. Other games are not supported.
+
+// Setup properties (AB, AW, AE) are applied before the move of a node, although a node should
+// never mix them (see PropertyType). A move captures the opponent's groups left without liberties
+// first; if the played group then has no liberties itself, it is removed as a suicide.
+
+use crate::codec;
+use crate::errors::{ErrorKind, SgfParseError};
+use crate::game_tree::GameTree;
+use crate::navigation::Location;
+use crate::node::Node;
+use crate::point::{BoardSize, Move, Point};
+use crate::property::{Color, PropertyValue};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Board {
+    size: BoardSize,
+    // Row by row, starting at the upper left.
+    stones: Vec<Option<Color>>,
+}
+
+impl Board {
+    pub fn new(size: BoardSize) -> Self {
+        Board {
+            size,
+            stones: vec![None; size.width as usize * size.height as usize],
+        }
+    }
+
+    pub fn size(&self) -> BoardSize {
+        self.size
+    }
+
+    // `None` for points outside the board too.
+    pub fn get(&self, point: Point) -> Option<Color> {
+        self.index(point).and_then(|index| self.stones[index])
+    }
+
+    // Fails for points outside the board.
+    pub fn set(&mut self, point: Point, stone: Option<Color>) -> Result<(), SgfParseError> {
+        let index = self.index(point).ok_or_else(|| outside(point))?;
+        self.stones[index] = stone;
+
+        Ok(())
+    }
+
+    // Every point holding a stone of the given color.
+    pub fn stones(&self, color: Color) -> Vec<Point> {
+        self.points()
+            .filter(|point| self.get(*point) == Some(color))
+            .collect()
+    }
+
+    // Plays a stone and returns the stones it captured. A suicide returns the player's own stones.
+    pub fn play(&mut self, color: Color, point: Point) -> Result<Vec<Point>, SgfParseError> {
+        if self.get(point).is_some() {
+            return Err(illegal_move(format!("{} is occupied", point.to_sgf())));
+        }
+
+        self.set(point, Some(color))?;
+
+        let mut captured = vec![];
+        for neighbour in self.neighbours(point) {
+            if self.get(neighbour) == Some(color.opponent()) {
+                let group = self.group(neighbour);
+                if self.liberties(&group) == 0 {
+                    captured.extend(self.remove(group));
+                }
+            }
+        }
+
+        if captured.is_empty() {
+            let group = self.group(point);
+            if self.liberties(&group) == 0 {
+                captured.extend(self.remove(group));
+            }
+        }
+
+        Ok(captured)
+    }
+
+    // All stones connected to the stone at `point`, including itself.
+    pub fn group(&self, point: Point) -> Vec<Point> {
+        let color = match self.get(point) {
+            Some(color) => color,
+            None => return vec![],
+        };
+
+        let mut group = vec![point];
+        let mut index = 0;
+        while index < group.len() {
+            for neighbour in self.neighbours(group[index]) {
+                if self.get(neighbour) == Some(color) && !group.contains(&neighbour) {
+                    group.push(neighbour);
+                }
+            }
+            index += 1;
+        }
+
+        group
+    }
+
+    pub fn liberties(&self, group: &[Point]) -> usize {
+        let mut liberties: Vec<Point> = group
+            .iter()
+            .flat_map(|point| self.neighbours(*point))
+            .filter(|neighbour| self.get(*neighbour).is_none())
+            .collect();
+        liberties.sort();
+        liberties.dedup();
+
+        liberties.len()
+    }
+
+    pub fn neighbours(&self, point: Point) -> Vec<Point> {
+        let mut neighbours = Vec::with_capacity(4);
+
+        if point.x > 0 {
+            neighbours.push(Point::new(point.x - 1, point.y));
+        }
+        if point.y > 0 {
+            neighbours.push(Point::new(point.x, point.y - 1));
+        }
+        if point.x + 1 < self.size.width {
+            neighbours.push(Point::new(point.x + 1, point.y));
+        }
+        if point.y + 1 < self.size.height {
+            neighbours.push(Point::new(point.x, point.y + 1));
+        }
+
+        neighbours
+    }
+
+    pub fn points(&self) -> impl Iterator<Item = Point> {
+        let BoardSize { width, height } = self.size;
+
+        (0..height).flat_map(move |y| (0..width).map(move |x| Point::new(x, y)))
+    }
+
+    fn remove(&mut self, group: Vec<Point>) -> Vec<Point> {
+        for point in &group {
+            if let Some(index) = self.index(*point) {
+                self.stones[index] = None;
+            }
+        }
+
+        group
+    }
+
+    fn index(&self, point: Point) -> Option<usize> {
+        self.size
+            .contains(point)
+            .then(|| point.y as usize * self.size.width as usize + point.x as usize)
+    }
+}
+
+// The number of stones each player has captured.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Prisoners {
+    // White stones captured by Black.
+    pub black: u32,
+    // Black stones captured by White.
+    pub white: u32,
+}
+
+impl Prisoners {
+    fn add(&mut self, captured_by: Color, count: usize) {
+        match captured_by {
+            Color::Black => self.black += count as u32,
+            Color::White => self.white += count as u32,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoardState {
+    pub board: Board,
+    pub prisoners: Prisoners,
+    pub to_move: Color,
+}
+
+impl BoardState {
+    // The empty board before the root node. With a handicap, White moves first.
+    pub fn start(tree: &GameTree) -> Self {
+        let root = tree.root();
+
+        let size = root
+            .get("SZ")
+            .and_then(|prop| prop.values.first())
+            .and_then(BoardSize::from_value)
+            .unwrap_or_default();
+
+        let handicap = matches!(
+            root.get("HA").and_then(|prop| prop.values.first()),
+            Some(PropertyValue::Number(stones, _, _)) if *stones >= 2
+        );
+
+        BoardState {
+            board: Board::new(size),
+            prisoners: Prisoners::default(),
+            to_move: if handicap { Color::White } else { Color::Black },
+        }
+    }
+
+    // Applies the setup, move and PL properties of a node.
+    pub fn apply(&mut self, node: &Node) -> Result<(), SgfParseError> {
+        for (id, stone) in [
+            ("AE", None),
+            ("AB", Some(Color::Black)),
+            ("AW", Some(Color::White)),
+        ] {
+            if let Some(property) = node.get(id) {
+                for point in property.points() {
+                    self.board.set(point, stone)?;
+                }
+            }
+        }
+
+        for (id, color) in [("B", Color::Black), ("W", Color::White)] {
+            let played = match node.get(id).and_then(|prop| prop.values.first()) {
+                Some(PropertyValue::Move(played)) => played,
+                _ => continue,
+            };
+
+            if let &Move::Play(point) = played {
+                let captured = self.board.play(color, point)?;
+                let captured_by = match captured.first() {
+                    // A suicide hands the player's own stones to the opponent.
+                    Some(first) if *first == point => color.opponent(),
+                    _ => color,
+                };
+                self.prisoners.add(captured_by, captured.len());
+            }
+
+            self.to_move = color.opponent();
+        }
+
+        if let Some(PropertyValue::Color(color)) =
+            node.get("PL").and_then(|prop| prop.values.first())
+        {
+            self.to_move = *color;
+        }
+
+        Ok(())
+    }
+}
+
+// Replays the nodes from the root to the node at `path` (see `GameTree::node_at`), returning the
+// state after each of them; the first state is the one after the root node.
+pub fn replay(tree: &GameTree, path: &[usize]) -> Result<Vec<BoardState>, SgfParseError> {
+    if tree.game() != codec::GO {
+        return Err(SgfParseError::new(ErrorKind::UnsupportedGame(tree.game())));
+    }
+
+    let mut location = Location::root(tree);
+    let mut state = BoardState::start(tree);
+    let mut states = Vec::with_capacity(path.len() + 1);
+
+    for step in 0..=path.len() {
+        state
+            .apply(location.node())
+            .map_err(|err| err.with_node(node_index(tree, location.node())))?;
+        states.push(state.clone());
+
+        if let Some(index) = path.get(step) {
+            location = location.child(*index).ok_or_else(|| {
+                SgfParseError::new(ErrorKind::InvalidPath(path[..=step].to_vec()))
+            })?;
+        }
+    }
+
+    Ok(states)
+}
+
+fn node_index(tree: &GameTree, node: &Node) -> usize {
+    tree.depth_first()
+        .position(|other| std::ptr::eq(other, node))
+        .unwrap_or_default()
+}
+
+fn illegal_move(details: String) -> SgfParseError {
+    SgfParseError::new(ErrorKind::IllegalMove(details))
+}
+
+fn outside(point: Point) -> SgfParseError {
+    illegal_move(format!("{} is outside the board", point.to_sgf()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{replay, Board, Prisoners};
+    use crate::errors::ErrorKind;
+    use crate::point::{BoardSize, Point};
+    use crate::property::Color;
+
+    fn example() -> crate::Collection {
+        crate::parse_file("resources/example.sgf").unwrap()
+    }
+
+    #[test]
+    fn can_capture_single_stone() {
+        let mut board = Board::new(BoardSize::default());
+
+        board.play(Color::White, Point::new(1, 0)).unwrap();
+        board.play(Color::Black, Point::new(0, 0)).unwrap();
+        let captured = board.play(Color::White, Point::new(0, 1)).unwrap();
+
+        assert_eq!(captured, vec![Point::new(0, 0)]);
+        assert_eq!(board.get(Point::new(0, 0)), None);
+    }
+
+    #[test]
+    fn point_outside_board_is_not_on_it() {
+        let mut board = Board::new(BoardSize {
+            width: 9,
+            height: 9,
+        });
+
+        assert!(board.set(Point::new(12, 0), Some(Color::Black)).is_err());
+        assert!(board.set(Point::new(0, 9), Some(Color::Black)).is_err());
+        assert!(board.play(Color::Black, Point::new(9, 0)).is_err());
+        assert_eq!(board.get(Point::new(12, 0)), None);
+        assert_eq!(board.get(Point::new(3, 1)), None);
+        assert!(board.stones(Color::Black).is_empty());
+    }
+
+    #[test]
+    fn suicide_removes_own_group() {
+        let mut board = Board::new(BoardSize {
+            width: 3,
+            height: 3,
+        });
+
+        board.play(Color::White, Point::new(1, 0)).unwrap();
+        board.play(Color::White, Point::new(0, 1)).unwrap();
+        let captured = board.play(Color::Black, Point::new(0, 0)).unwrap();
+
+        assert_eq!(captured, vec![Point::new(0, 0)]);
+        assert!(board.stones(Color::Black).is_empty());
+    }
+
+    #[test]
+    fn playing_on_occupied_point_is_error() {
+        let mut board = Board::new(BoardSize::default());
+        board.play(Color::Black, Point::new(3, 3)).unwrap();
+
+        let error = board.play(Color::White, Point::new(3, 3)).err().unwrap();
+        assert!(matches!(error.kind(), ErrorKind::IllegalMove(_)));
+    }
+
+    #[test]
+    fn replays_moves_and_passes() {
+        let collection = example();
+        let tree = &collection.game_trees()[0];
+
+        // The first variation ends with a white and a black pass.
+        let path = vec![0; 13];
+        let states = replay(tree, &path).unwrap();
+
+        assert_eq!(states.len(), 14);
+        assert_eq!(states[1].board.get(Point::new(15, 3)), Some(Color::Black));
+        assert_eq!(states[1].to_move, Color::White);
+        assert_eq!(states[2].board.get(Point::new(3, 15)), Some(Color::White));
+        assert_eq!(states[2].to_move, Color::Black);
+
+        let last = states.last().unwrap();
+        assert_eq!(last.board.stones(Color::Black).len(), 6);
+        assert_eq!(last.board.stones(Color::White).len(), 5);
+        assert_eq!(last.to_move, Color::White);
+    }
+
+    #[test]
+    fn replays_setup_and_player_to_move() {
+        let collection = example();
+        let tree = &collection.game_trees()[0];
+
+        let states = replay(tree, &[1, 0, 0, 0]).unwrap();
+
+        // AB[dd][de][df][dg][do:gq] AW[jd][je][jf][jg][kn:lq][pn:pq]
+        assert_eq!(states[1].board.stones(Color::Black).len(), 16);
+        assert_eq!(states[1].board.stones(Color::White).len(), 16);
+
+        // AE[ep][fp][kn][lo][lq][pn:pq]
+        assert_eq!(states[2].board.stones(Color::Black).len(), 14);
+        assert_eq!(states[2].board.stones(Color::White).len(), 9);
+
+        assert_eq!(states[3].to_move, Color::Black);
+        assert_eq!(states[4].to_move, Color::White);
+        assert_eq!(states[4].prisoners, Prisoners::default());
+    }
+
+    #[test]
+    fn counts_captures_and_suicide() {
+        let collection = example();
+        let tree = &collection.game_trees()[0];
+
+        let mut path = vec![4];
+        path.extend([0; 20]);
+        let states = replay(tree, &path).unwrap();
+
+        // B[rs]: One white stone at s2 captured.
+        assert_eq!(states[7].prisoners.black, 1);
+        // B[os]: Two white stones captured (at q1 & r1).
+        assert_eq!(states[11].prisoners.black, 3);
+
+        // B[qs]: Suicide move (all B stones get captured).
+        let last = states.last().unwrap();
+        let before = &states[states.len() - 2];
+        let black_before = before.board.stones(Color::Black).len() as u32;
+
+        assert!(last.board.stones(Color::Black).is_empty());
+        assert_eq!(
+            last.prisoners.white,
+            before.prisoners.white + black_before + 1
+        );
+    }
+
+    #[test]
+    fn invalid_path_is_error() {
+        let collection = example();
+        let tree = &collection.game_trees()[0];
+
+        let error = replay(tree, &[5]).err().unwrap();
+        assert_eq!(*error.kind(), ErrorKind::InvalidPath(vec![5]));
+    }
+
+    #[test]
+    fn only_go_can_be_replayed() {
+        let collection = crate::parse_str("(;GM[2];B[dc])").unwrap();
+
+        let error = replay(&collection.game_trees()[0], &[0]).err().unwrap();
+        assert_eq!(*error.kind(), ErrorKind::UnsupportedGame(2));
+    }
+
+    #[test]
+    fn handicap_gives_white_first_move() {
+        let collection = crate::parse_str("(;SZ[9]HA[2]AB[cc][gg])").unwrap();
+        let states = replay(&collection.game_trees()[0], &[]).unwrap();
+
+        assert_eq!(states[0].to_move, Color::White);
+        assert_eq!(states[0].board.size().width, 9);
+        assert_eq!(states[0].board.stones(Color::Black).len(), 2);
+    }
+}
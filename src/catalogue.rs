@@ -0,0 +1,223 @@
+// The properties defined by the SGF FF[4] spec (https://www.red-bean.com/sgf/properties.html),
+// along with the Go specific properties (https://www.red-bean.com/sgf/go.html).
+
+// Older versions of the spec (https://www.red-bean.com/sgf/ff1_3/) define a few properties that
+// were dropped in FF[4], and FF[3] labels are composed with text rather than simple text. These
+// are only known in files of those versions, see `definition_for`.
+
+// Lists of points may be compressed, e.g. AB[do:gq] instead of listing all 12 stones.
+// This only applies to `List` and `EList` properties whose value type is Point or Stone.
+
+use crate::property::PropertyType;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValueType {
+    None,
+    // Number with a range.
+    Number(u32, u32),
+    Real,
+    Double,
+    Color,
+    SimpleText,
+    Text,
+    Point,
+    Move,
+    Stone,
+    // A game result, see `GameResult`.
+    Result,
+    // A list of dates, see `PartialDate`.
+    Date,
+    // An overtime description, see `Overtime`.
+    Overtime,
+    // A player's rank, see `Rank`.
+    Rank,
+    Compose(&'static ValueType, &'static ValueType),
+    // Either of the two types, e.g. SZ is `number | composed number ":" number`.
+    Or(&'static ValueType, &'static ValueType),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueCount {
+    // Exactly one value.
+    Single,
+    // One or more values.
+    List,
+    // Like `List`, but may also be empty, i.e. a single `[]`.
+    EList,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PropertyDefinition {
+    pub value_type: ValueType,
+    pub count: ValueCount,
+    // `None` for properties without a type ("-" in the spec), which may appear anywhere.
+    pub property_type: Option<PropertyType>,
+}
+
+impl PropertyDefinition {
+    // Lists of points that may be compressed, e.g. AB, TR or VW.
+    pub fn is_point_list(&self) -> bool {
+        matches!(self.value_type, ValueType::Point | ValueType::Stone)
+            && self.count != ValueCount::Single
+    }
+}
+
+const POINT_POINT: ValueType = ValueType::Compose(&ValueType::Point, &ValueType::Point);
+const UNBOUNDED: u32 = u32::MAX;
+// DT, see `Property::decode_raw`.
+pub const DATE_OR_TEXT: ValueType = ValueType::Or(&ValueType::Date, &ValueType::SimpleText);
+// RE, see `Property::decode_raw`.
+pub const RESULT_OR_TEXT: ValueType = ValueType::Or(&ValueType::Result, &ValueType::SimpleText);
+
+pub fn definition(id: &str) -> Option<PropertyDefinition> {
+    use ValueCount::*;
+    use ValueType::*;
+
+    let (value_type, count) = match id {
+        // Move properties.
+        "B" | "W" => (Move, Single),
+        "KO" => (None, Single),
+        "MN" => (Number(0, UNBOUNDED), Single),
+
+        // Setup properties.
+        "AB" | "AW" => (Stone, List),
+        "AE" => (Point, List),
+        "PL" => (Color, Single),
+
+        // Node annotation properties.
+        "C" => (Text, Single),
+        "DM" | "GB" | "GW" | "HO" | "UC" => (Double, Single),
+        "N" => (SimpleText, Single),
+        "V" => (Real, Single),
+
+        // Move annotation properties.
+        "BM" | "TE" => (Double, Single),
+        "DO" | "IT" => (None, Single),
+
+        // Markup properties.
+        "AR" | "LN" => (POINT_POINT, List),
+        "CR" | "MA" | "SL" | "SQ" | "TR" => (Point, List),
+        "DD" => (Point, EList),
+        "LB" => (Compose(&Point, &SimpleText), List),
+
+        // Root properties.
+        "AP" => (Compose(&SimpleText, &SimpleText), Single),
+        "CA" => (SimpleText, Single),
+        "FF" => (Number(1, 4), Single),
+        "GM" => (Number(1, UNBOUNDED), Single),
+        "ST" => (Number(0, 3), Single),
+        "SZ" => (
+            Or(&Number(1, 52), &Compose(&Number(1, 52), &Number(1, 52))),
+            Single,
+        ),
+
+        // Game info properties.
+        "AN" | "BT" | "CP" | "EV" | "GN" | "ON" | "PB" | "PC" | "PW" | "RO" | "RU" | "SO"
+        | "US" | "WT" => (SimpleText, Single),
+        // Dates that aren't in the ISO format are kept as text, with a warning.
+        "DT" => (DATE_OR_TEXT, Single),
+        "GC" => (Text, Single),
+        // Results that aren't recognised are kept as text, with a warning.
+        "RE" => (RESULT_OR_TEXT, Single),
+        "OT" => (Overtime, Single),
+        // Ranks that aren't recognised are kept as text.
+        "BR" | "WR" => (Or(&Rank, &SimpleText), Single),
+        "TM" => (Real, Single),
+
+        // Timing properties.
+        "BL" | "WL" => (Real, Single),
+        "OB" | "OW" => (Number(0, UNBOUNDED), Single),
+
+        // Miscellaneous properties.
+        "FG" => (
+            Or(&None, &Compose(&Number(0, UNBOUNDED), &SimpleText)),
+            Single,
+        ),
+        "PM" => (Number(0, 2), Single),
+        "VW" => (Point, EList),
+
+        // Go specific properties.
+        "HA" => (Number(2, UNBOUNDED), Single),
+        "KM" => (Real, Single),
+        "TB" | "TW" => (Point, EList),
+
+        _ => return Option::None,
+    };
+
+    let property_type = match id {
+        "B" | "W" | "KO" | "MN" | "BM" | "DO" | "IT" | "TE" | "BL" | "WL" | "OB" | "OW" => {
+            Some(PropertyType::Move)
+        }
+        "AB" | "AE" | "AW" | "PL" => Some(PropertyType::Setup),
+        "AP" | "CA" | "FF" | "GM" | "ST" | "SZ" => Some(PropertyType::Root),
+        "AN" | "BR" | "BT" | "CP" | "DT" | "EV" | "GC" | "GN" | "ON" | "OT" | "PB" | "PC"
+        | "PW" | "RE" | "RO" | "RU" | "SO" | "TM" | "US" | "WR" | "WT" | "HA" | "KM" => {
+            Some(PropertyType::GameInfo)
+        }
+        _ => Option::None,
+    };
+
+    Some(PropertyDefinition {
+        value_type,
+        count,
+        property_type,
+    })
+}
+
+// The definition of a property in files of the given FF version.
+pub fn definition_for(id: &str, version: u32) -> Option<PropertyDefinition> {
+    if version >= 4 {
+        return definition(id);
+    }
+
+    match id {
+        "LB" => Some(PropertyDefinition {
+            value_type: ValueType::Compose(&ValueType::Point, &ValueType::Text),
+            count: ValueCount::List,
+            property_type: None,
+        }),
+        _ => definition(id).or_else(|| legacy_definition(id)),
+    }
+}
+
+// Properties of FF[1] to FF[3] without an FF[4] counterpart of the same name. Property types were
+// only introduced in FF[4], so these have none.
+fn legacy_definition(id: &str) -> Option<PropertyDefinition> {
+    use ValueCount::*;
+    use ValueType::*;
+
+    let (value_type, count) = match id {
+        // Black and white species: 0 human, 1 modem, 2 computer.
+        "BS" | "WS" => (Number(0, UNBOUNDED), Single),
+        // Check mark.
+        "CH" => (Double, Single),
+        // Evaluation of a computer move, may be negative.
+        "EL" => (Real, Single),
+        // Expected next move.
+        "EX" => (Move, Single),
+        // Game identifier.
+        "ID" => (SimpleText, Single),
+        // Labels, lettered "a", "b", "c" and so on in order.
+        "L" => (Point, List),
+        // Enforces losing on time.
+        "LT" => (None, Single),
+        // Marked points.
+        "M" => (Point, List),
+        // Moves per overtime period, the length of a period and the operator overhead per move.
+        "OM" => (Number(0, UNBOUNDED), Single),
+        "OP" | "OV" => (Real, Single),
+        // Region of the board, secure stones and self-test moves.
+        "RG" | "SC" | "SE" => (Point, List),
+        // Sigma.
+        "SI" => (Real, Single),
+        // Territory count.
+        "TC" => (Number(0, UNBOUNDED), Single),
+        _ => return Option::None,
+    };
+
+    Some(PropertyDefinition {
+        value_type,
+        count,
+        property_type: Option::None,
+    })
+}
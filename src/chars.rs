@@ -3,3 +3,5 @@ pub const TREE_START: char = '(';
 pub const NODE_START: char = ';';
 pub const PROP_VAL_START: char = '[';
 pub const PROP_VAL_END: char = ']';
+pub const ESCAPE: char = '\\';
+pub const COMPOSE_SEPARATOR: char = ':';
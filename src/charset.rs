@@ -0,0 +1,201 @@
+// From the SGF spec (https://www.red-bean.com/sgf/properties.html#CA):
+// CA provides the used charset for SimpleText and Text type. Default value is 'ISO-8859-1' aka
+// 'Latin1'. Only charset names (or their aliases) as specified in RFC 1345 (or updates thereof)
+// are allowed.
+
+// Many files without CA are UTF-8 in practice, so bytes that are valid UTF-8 are read as such
+// and Latin-1 is only used for the rest. Encodings like Shift_JIS may use the bytes of "\" and "]"
+// within a character, so the whole source is decoded before it's lexed.
+
+use crate::errors::{ErrorKind, SgfParseError};
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
+
+// Source decoded to UTF-8, along with the encoding it was read with.
+pub struct Decoded {
+    pub text: String,
+    pub encoding: &'static Encoding,
+    // Problems that didn't stop decoding, e.g. an unknown charset.
+    pub warnings: Vec<SgfParseError>,
+}
+
+pub fn decode(bytes: &[u8]) -> Decoded {
+    let mut warnings = vec![];
+
+    let declared = root_charset(bytes).and_then(|label| {
+        let encoding = Encoding::for_label(label.trim().as_bytes());
+        if encoding.is_none() {
+            warnings.push(charset_error(format!("Unknown charset '{}'", label)));
+        }
+        encoding
+    });
+
+    let encoding = match declared {
+        Some(encoding) => encoding,
+        None if std::str::from_utf8(bytes).is_ok() => UTF_8,
+        // Latin-1 is read as its superset Windows-1252, as is common practice.
+        None => WINDOWS_1252,
+    };
+
+    let (text, _, had_errors) = encoding.decode(bytes);
+    if had_errors {
+        warnings.push(charset_error(format!(
+            "Source is not valid {}; invalid bytes were replaced",
+            encoding.name()
+        )));
+    }
+
+    Decoded {
+        text: text.into_owned(),
+        encoding,
+        warnings,
+    }
+}
+
+// Encodes text in the given charset. Characters the charset can't represent are written as
+// numeric character references, e.g. "&#12354;".
+pub fn encode(text: &str, encoding: &'static Encoding) -> Vec<u8> {
+    let (bytes, _, _) = encoding.encode(text);
+
+    bytes.into_owned()
+}
+
+// The value of CA in the root node of the first GameTree, found without decoding the source.
+// Property identifiers and the charset names are ASCII, which every supported charset agrees on.
+// Escapes aren't honoured, as the second byte of a character may be "\" in e.g. Shift_JIS, so a
+// value ends at its first "]".
+fn root_charset(bytes: &[u8]) -> Option<String> {
+    let mut ident = vec![];
+    let mut index = root_start(bytes)?;
+
+    while let Some(&byte) = bytes.get(index) {
+        match byte {
+            b'[' => {
+                let end = index + 1 + bytes[index + 1..].iter().position(|byte| *byte == b']')?;
+                if ident == b"CA" {
+                    return Some(String::from_utf8_lossy(&bytes[index + 1..end]).into_owned());
+                }
+
+                index = end + 1;
+                // Further values of the same property follow directly after it.
+                if bytes.get(skip_whitespace(bytes, index)) != Some(&b'[') {
+                    ident.clear();
+                }
+                continue;
+            }
+            // The end of the root node.
+            b';' | b'(' | b')' => return None,
+            b'A'..=b'Z' => ident.push(byte),
+            // Before FF[4] identifiers may contain lowercase letters, e.g. "CharSet".
+            b'a'..=b'z' => {}
+            _ if byte.is_ascii_whitespace() => {}
+            // E.g. the rest of a value that contained an escaped "]".
+            _ => ident.clear(),
+        }
+
+        index += 1;
+    }
+
+    None
+}
+
+// The index after the ";" of the first "(;", which may have white space in between. Text before
+// it, e.g. junk skipped in lenient mode, may contain a ";" of its own.
+fn root_start(bytes: &[u8]) -> Option<usize> {
+    bytes
+        .iter()
+        .enumerate()
+        .filter(|(_, byte)| **byte == b'(')
+        .map(|(index, _)| skip_whitespace(bytes, index + 1))
+        .find(|index| bytes.get(*index) == Some(&b';'))
+        .map(|index| index + 1)
+}
+
+fn skip_whitespace(bytes: &[u8], mut index: usize) -> usize {
+    while bytes.get(index).is_some_and(u8::is_ascii_whitespace) {
+        index += 1;
+    }
+
+    index
+}
+
+fn charset_error(details: String) -> SgfParseError {
+    SgfParseError::new(ErrorKind::Charset(details))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, encode};
+    use crate::errors::ErrorKind;
+    use encoding_rs::{Encoding, EUC_KR, GBK, SHIFT_JIS, UTF_8, WINDOWS_1252};
+    use test_case::test_case;
+
+    #[test_case(GBK, "(;CA[GB2312]PB[古力])" ; "GB2312")]
+    #[test_case(SHIFT_JIS, "(;CA[Shift_JIS]PB[本因坊秀策])" ; "Shift JIS")]
+    #[test_case(EUC_KR, "(;CA[EUC-KR]PB[이세돌])" ; "EUC KR")]
+    #[test_case(WINDOWS_1252, "(;CA[ISO-8859-1]PB[Schäfer])" ; "Latin 1")]
+    fn can_decode_declared_charset(encoding: &'static Encoding, content: &str) {
+        let bytes = encode(content, encoding);
+        let decoded = decode(&bytes);
+
+        assert_eq!(decoded.encoding, encoding);
+        assert_eq!(decoded.text, content);
+        assert!(decoded.warnings.is_empty());
+    }
+
+    #[test]
+    fn shift_jis_may_contain_escape_bytes() {
+        // The second byte of "ソ" is 0x5C, i.e. "\".
+        let bytes = encode("(;CA[SJIS]C[ソ]PB[x])", SHIFT_JIS);
+        assert!(bytes.contains(&b'\\'));
+
+        assert_eq!(decode(&bytes).text, "(;CA[SJIS]C[ソ]PB[x])");
+    }
+
+    #[test]
+    fn charset_after_escape_byte_is_found() {
+        // The second bytes of "ソ" and "本" are 0x5C, i.e. "\".
+        let content = "(;FF[4]PB[ソ]CA[Shift_JIS]PW[本因坊])";
+        let decoded = decode(&encode(content, SHIFT_JIS));
+
+        assert_eq!(decoded.encoding, SHIFT_JIS);
+        assert_eq!(decoded.text, content);
+    }
+
+    #[test_case(b"(;C[caf\xc3\xa9])", UTF_8 ; "UTF-8 without CA")]
+    #[test_case(b"(;C[caf\xe9])", WINDOWS_1252 ; "Latin 1 without CA")]
+    fn detects_charset_without_ca(bytes: &[u8], encoding: &'static Encoding) {
+        let decoded = decode(bytes);
+
+        assert_eq!(decoded.encoding, encoding);
+        assert_eq!(decoded.text, "(;C[café])");
+    }
+
+    #[test]
+    fn charset_is_only_read_from_root() {
+        let decoded = decode(b"(;C[root];CA[Shift_JIS])");
+
+        assert_eq!(decoded.encoding, UTF_8);
+    }
+
+    #[test_case(b"Re: game; see below\n(;CA[GB2312]PB[x])" ; "Junk with semicolon")]
+    #[test_case(b"(x; y)\n( \n;CA[GB2312]PB[x])" ; "Parenthesis without node")]
+    fn charset_is_read_after_leading_junk(bytes: &[u8]) {
+        assert_eq!(decode(bytes).encoding, GBK);
+    }
+
+    #[test]
+    fn unknown_charset_is_warning() {
+        let decoded = decode(b"(;CA[Klingon]C[x])");
+
+        assert_eq!(decoded.encoding, UTF_8);
+        assert!(matches!(decoded.warnings[0].kind(), ErrorKind::Charset(_)));
+    }
+
+    #[test]
+    fn invalid_bytes_are_replaced() {
+        let decoded = decode(b"(;CA[UTF-8]C[\xff])");
+
+        assert_eq!(decoded.text, "(;CA[UTF-8]C[\u{fffd}])");
+        assert_eq!(decoded.warnings.len(), 1);
+    }
+}
@@ -0,0 +1,630 @@
+// From the SGF spec (https://www.red-bean.com/sgf/sgf4.html#types):
+// Point, Move and Stone are game specific; GM in the root node says which game a tree belongs to.
+// A codec reads and writes these values for one game. Games without a registered codec keep
+// their values as written, see `PropertyValue::Unknown`.
+
+// The codecs shipped with the crate use two notations:
+// - Letter pairs, as in Go: column then row, "a"-"z" and "A"-"Z", counted from the upper left.
+//   Moves are one point to place a piece, two to move one (e.g. "bacd") and three for Amazons,
+//   where the last point is the arrow.
+// - Algebraic, as in Chess: a column letter followed by a row number counted from the bottom,
+//   e.g. "e4". Moves are one point to place a piece, or two to move one (e.g. "e2e4" or "e2-e4").
+// Hex points are a column letter followed by a row number counted from the top, e.g. "a1"; its
+// other moves, e.g. "swap-sides" or "resign", are kept as written. Backgammon points are single
+// letters, and its moves are kept as written.
+
+use crate::errors::{ErrorKind, SgfParseError};
+use crate::point::{BoardSize, Move, Point};
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+pub const GO: u32 = 1;
+pub const OTHELLO: u32 = 2;
+pub const CHESS: u32 = 3;
+pub const GOMOKU: u32 = 4;
+pub const NINE_MENS_MORRIS: u32 = 5;
+pub const BACKGAMMON: u32 = 6;
+pub const LINES_OF_ACTION: u32 = 9;
+pub const HEX: u32 = 11;
+pub const AMAZONS: u32 = 18;
+
+pub trait GameCodec: Send + Sync {
+    // The GM number of the game.
+    fn game(&self) -> u32;
+
+    // The board size used when a game has no SZ.
+    fn default_size(&self) -> BoardSize;
+
+    // Also used for stones.
+    fn parse_point(&self, val: &str, size: BoardSize) -> Result<Point, SgfParseError>;
+
+    fn parse_move(&self, val: &str, size: BoardSize) -> Result<Move, SgfParseError>;
+
+    fn point_to_sgf(&self, point: Point, size: BoardSize) -> String;
+
+    fn move_to_sgf(&self, played: &Move, size: BoardSize) -> String;
+}
+
+// The codecs to choose from by GM, starting with the ones shipped with the crate.
+#[derive(Clone)]
+pub struct Codecs {
+    codecs: BTreeMap<u32, Arc<dyn GameCodec>>,
+}
+
+impl Codecs {
+    // No codecs at all, not even Go.
+    pub fn empty() -> Self {
+        Codecs {
+            codecs: BTreeMap::new(),
+        }
+    }
+
+    // Adds a codec, replacing any codec for the same game.
+    pub fn register<C: GameCodec + 'static>(&mut self, codec: C) {
+        self.codecs.insert(codec.game(), Arc::new(codec));
+    }
+
+    pub fn get(&self, game: u32) -> Option<Arc<dyn GameCodec>> {
+        self.codecs.get(&game).cloned()
+    }
+
+    pub fn games(&self) -> impl Iterator<Item = u32> + '_ {
+        self.codecs.keys().copied()
+    }
+}
+
+impl Default for Codecs {
+    fn default() -> Self {
+        let mut codecs = Codecs::empty();
+
+        codecs.register(Go);
+        codecs.register(Letters::new(OTHELLO, 8));
+        codecs.register(Algebraic::new(CHESS, 8));
+        codecs.register(Letters::new(GOMOKU, 15));
+        codecs.register(Algebraic::new(NINE_MENS_MORRIS, 7));
+        codecs.register(Backgammon);
+        codecs.register(Letters::new(LINES_OF_ACTION, 8));
+        codecs.register(Hex);
+        codecs.register(Letters::new(AMAZONS, 10));
+
+        codecs
+    }
+}
+
+impl Debug for Codecs {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.games()).finish()
+    }
+}
+
+// Codecs are compared by the games they cover.
+impl PartialEq for Codecs {
+    fn eq(&self, other: &Self) -> bool {
+        self.games().eq(other.games())
+    }
+}
+
+impl Eq for Codecs {}
+
+pub struct Go;
+
+impl GameCodec for Go {
+    fn game(&self) -> u32 {
+        GO
+    }
+
+    fn default_size(&self) -> BoardSize {
+        BoardSize::default()
+    }
+
+    fn parse_point(&self, val: &str, size: BoardSize) -> Result<Point, SgfParseError> {
+        Point::parse(val, size)
+    }
+
+    fn parse_move(&self, val: &str, size: BoardSize) -> Result<Move, SgfParseError> {
+        Move::parse(val, size)
+    }
+
+    fn point_to_sgf(&self, point: Point, _: BoardSize) -> String {
+        point.to_sgf()
+    }
+
+    fn move_to_sgf(&self, played: &Move, size: BoardSize) -> String {
+        Letters::new(GO, 19).move_to_sgf(played, size)
+    }
+}
+
+// Games written with Go's letter pairs, on a square board.
+pub struct Letters {
+    game: u32,
+    size: u8,
+}
+
+impl Letters {
+    pub fn new(game: u32, size: u8) -> Self {
+        Letters { game, size }
+    }
+}
+
+impl GameCodec for Letters {
+    fn game(&self) -> u32 {
+        self.game
+    }
+
+    fn default_size(&self) -> BoardSize {
+        BoardSize {
+            width: self.size,
+            height: self.size,
+        }
+    }
+
+    fn parse_point(&self, val: &str, size: BoardSize) -> Result<Point, SgfParseError> {
+        Point::parse(val, size)
+    }
+
+    fn parse_move(&self, val: &str, size: BoardSize) -> Result<Move, SgfParseError> {
+        if val.is_empty() {
+            return Ok(Move::Pass);
+        }
+        if !val.is_ascii() || !val.len().is_multiple_of(2) {
+            return Ok(Move::Other(String::from(val)));
+        }
+
+        // Values that aren't points, e.g. "resign", are kept as written.
+        let points = match (0..val.len())
+            .step_by(2)
+            .map(|index| Point::parse(&val[index..index + 2], size))
+            .collect::<Result<Vec<Point>, SgfParseError>>()
+        {
+            Ok(points) => points,
+            Err(_) => return Ok(Move::Other(String::from(val))),
+        };
+
+        Ok(match points.as_slice() {
+            [point] => Move::Play(*point),
+            [from, to] => Move::Step(*from, *to),
+            [from, to, arrow] => Move::Shot(*from, *to, *arrow),
+            _ => Move::Other(String::from(val)),
+        })
+    }
+
+    fn point_to_sgf(&self, point: Point, _: BoardSize) -> String {
+        point.to_sgf()
+    }
+
+    fn move_to_sgf(&self, played: &Move, _: BoardSize) -> String {
+        match played {
+            Move::Pass => String::new(),
+            Move::Play(point) => point.to_sgf(),
+            Move::Step(from, to) => format!("{}{}", from.to_sgf(), to.to_sgf()),
+            Move::Shot(from, to, arrow) => {
+                format!("{}{}{}", from.to_sgf(), to.to_sgf(), arrow.to_sgf())
+            }
+            Move::Other(raw) => raw.clone(),
+        }
+    }
+}
+
+// Games written in algebraic notation, on a square board.
+pub struct Algebraic {
+    game: u32,
+    size: u8,
+}
+
+impl Algebraic {
+    pub fn new(game: u32, size: u8) -> Self {
+        Algebraic { game, size }
+    }
+
+    // Splits off a leading point, e.g. "e2" from "e2e4".
+    fn split_point(val: &str, size: BoardSize) -> Option<(Point, &str)> {
+        let column = val.chars().next().filter(char::is_ascii_lowercase)?;
+        let digits = val[1..]
+            .find(|c: char| !c.is_ascii_digit())
+            .map_or(val.len(), |index| index + 1);
+
+        let row: u8 = val[1..digits].parse().ok()?;
+        if row == 0 || row > size.height {
+            return None;
+        }
+
+        let point = Point::new(column as u8 - b'a', size.height - row);
+        size.contains(point).then_some((point, &val[digits..]))
+    }
+}
+
+impl GameCodec for Algebraic {
+    fn game(&self) -> u32 {
+        self.game
+    }
+
+    fn default_size(&self) -> BoardSize {
+        BoardSize {
+            width: self.size,
+            height: self.size,
+        }
+    }
+
+    fn parse_point(&self, val: &str, size: BoardSize) -> Result<Point, SgfParseError> {
+        match Algebraic::split_point(val, size) {
+            Some((point, "")) => Ok(point),
+            _ => Err(invalid_point(val)),
+        }
+    }
+
+    fn parse_move(&self, val: &str, size: BoardSize) -> Result<Move, SgfParseError> {
+        let played = match Algebraic::split_point(val, size) {
+            Some((point, "")) => Move::Play(point),
+            Some((from, rest)) => {
+                match Algebraic::split_point(rest.strip_prefix('-').unwrap_or(rest), size) {
+                    Some((to, "")) => Move::Step(from, to),
+                    _ => Move::Other(String::from(val)),
+                }
+            }
+            None if val.is_empty() => Move::Pass,
+            // E.g. castling or a promotion.
+            None => Move::Other(String::from(val)),
+        };
+
+        Ok(played)
+    }
+
+    fn point_to_sgf(&self, point: Point, size: BoardSize) -> String {
+        format!("{}{}", (b'a' + point.x) as char, size.height - point.y)
+    }
+
+    fn move_to_sgf(&self, played: &Move, size: BoardSize) -> String {
+        match played {
+            Move::Pass => String::new(),
+            Move::Play(point) => self.point_to_sgf(*point, size),
+            Move::Step(from, to) => format!(
+                "{}{}",
+                self.point_to_sgf(*from, size),
+                self.point_to_sgf(*to, size)
+            ),
+            Move::Shot(..) | Move::Other(_) => {
+                Letters::new(self.game, self.size).move_to_sgf(played, size)
+            }
+        }
+    }
+}
+
+pub struct Hex;
+
+impl GameCodec for Hex {
+    fn game(&self) -> u32 {
+        HEX
+    }
+
+    fn default_size(&self) -> BoardSize {
+        BoardSize {
+            width: 11,
+            height: 11,
+        }
+    }
+
+    fn parse_point(&self, val: &str, size: BoardSize) -> Result<Point, SgfParseError> {
+        let mut chars = val.chars();
+        let column = chars
+            .next()
+            .filter(char::is_ascii_lowercase)
+            .ok_or_else(|| invalid_point(val))?;
+        let digits = chars.as_str();
+        if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+            return Err(invalid_point(val));
+        }
+
+        let row: u8 = digits.parse().map_err(|_| invalid_point(val))?;
+        if row == 0 {
+            return Err(invalid_point(val));
+        }
+
+        let point = Point::new(column as u8 - b'a', row - 1);
+        if size.contains(point) {
+            Ok(point)
+        } else {
+            Err(invalid_point(val))
+        }
+    }
+
+    fn parse_move(&self, val: &str, size: BoardSize) -> Result<Move, SgfParseError> {
+        Ok(match self.parse_point(val, size) {
+            Ok(point) => Move::Play(point),
+            // E.g. "swap-sides" or "resign".
+            Err(_) => Move::Other(String::from(val)),
+        })
+    }
+
+    fn point_to_sgf(&self, point: Point, _: BoardSize) -> String {
+        format!("{}{}", (b'a' + point.x) as char, point.y + 1)
+    }
+
+    fn move_to_sgf(&self, played: &Move, size: BoardSize) -> String {
+        match played {
+            Move::Play(point) => self.point_to_sgf(*point, size),
+            _ => Letters::new(HEX, 11).move_to_sgf(played, size),
+        }
+    }
+}
+
+// Points are single letters "a"-"x"; moves are kept as written.
+pub struct Backgammon;
+
+impl GameCodec for Backgammon {
+    fn game(&self) -> u32 {
+        BACKGAMMON
+    }
+
+    fn default_size(&self) -> BoardSize {
+        BoardSize {
+            width: 24,
+            height: 1,
+        }
+    }
+
+    fn parse_point(&self, val: &str, _: BoardSize) -> Result<Point, SgfParseError> {
+        match val.as_bytes() {
+            [letter @ b'a'..=b'x'] => Ok(Point::new(letter - b'a', 0)),
+            _ => Err(invalid_point(val)),
+        }
+    }
+
+    fn parse_move(&self, val: &str, _: BoardSize) -> Result<Move, SgfParseError> {
+        Ok(Move::Other(String::from(val)))
+    }
+
+    fn point_to_sgf(&self, point: Point, _: BoardSize) -> String {
+        ((b'a' + point.x) as char).to_string()
+    }
+
+    fn move_to_sgf(&self, played: &Move, size: BoardSize) -> String {
+        Letters::new(BACKGAMMON, 24).move_to_sgf(played, size)
+    }
+}
+
+fn invalid_point(val: &str) -> SgfParseError {
+    SgfParseError::new(ErrorKind::InvalidValue(format!("'{}' is not a point", val)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Algebraic, Backgammon, Codecs, GameCodec, Hex, Letters, AMAZONS, CHESS, OTHELLO};
+    use crate::errors::ErrorKind;
+    use crate::parser::ParseOptions;
+    use crate::point::{BoardSize, Move, Point};
+    use crate::property::PropertyValue;
+    use crate::writer::WriteOptions;
+    use test_case::test_case;
+
+    fn square(size: u8) -> BoardSize {
+        BoardSize {
+            width: size,
+            height: size,
+        }
+    }
+
+    #[test]
+    fn default_codecs_cover_shipped_games() {
+        let games: Vec<u32> = Codecs::default().games().collect();
+
+        assert_eq!(games, vec![1, 2, 3, 4, 5, 6, 9, 11, 18]);
+    }
+
+    #[test_case("e4", Point::new(4, 4) ; "Centre")]
+    #[test_case("a1", Point::new(0, 7) ; "Lower left")]
+    #[test_case("h8", Point::new(7, 0) ; "Upper right")]
+    fn can_parse_algebraic_point(val: &str, expected: Point) {
+        let codec = Algebraic::new(CHESS, 8);
+
+        assert_eq!(codec.parse_point(val, square(8)).unwrap(), expected);
+        assert_eq!(codec.point_to_sgf(expected, square(8)), val);
+    }
+
+    #[test_case("a9" ; "Row outside board")]
+    #[test_case("i1" ; "Column outside board")]
+    #[test_case("a0" ; "Row zero")]
+    #[test_case("ee" ; "Letter pair")]
+    fn invalid_algebraic_point_is_error(val: &str) {
+        assert!(Algebraic::new(CHESS, 8)
+            .parse_point(val, square(8))
+            .is_err());
+    }
+
+    #[test_case("e2e4", Move::Step(Point::new(4, 6), Point::new(4, 4)) ; "Step")]
+    #[test_case("e2-e4", Move::Step(Point::new(4, 6), Point::new(4, 4)) ; "Step with dash")]
+    #[test_case("O-O", Move::Other(String::from("O-O")) ; "Castling")]
+    #[test_case("e7e8q", Move::Other(String::from("e7e8q")) ; "Promotion")]
+    fn can_parse_chess_move(val: &str, expected: Move) {
+        let codec = Algebraic::new(CHESS, 8);
+
+        assert_eq!(codec.parse_move(val, square(8)).unwrap(), expected);
+    }
+
+    #[test_case("", Move::Pass ; "Pass")]
+    #[test_case("fe", Move::Play(Point::new(5, 4)) ; "Place")]
+    #[test_case("dajdjg", Move::Shot(Point::new(3, 0), Point::new(9, 3), Point::new(9, 6)) ; "Amazons")]
+    #[test_case("bacd", Move::Step(Point::new(1, 0), Point::new(2, 3)) ; "Step")]
+    fn can_parse_letter_move(val: &str, expected: Move) {
+        let codec = Letters::new(AMAZONS, 26);
+        let played = codec.parse_move(val, square(26)).unwrap();
+
+        assert_eq!(played, expected);
+        assert_eq!(codec.move_to_sgf(&played, square(26)), val);
+    }
+
+    #[test_case("ii" ; "Outside board")]
+    #[test_case("resign" ; "Not points")]
+    fn other_letter_move_is_kept(val: &str) {
+        let played = Letters::new(OTHELLO, 8).parse_move(val, square(8)).unwrap();
+
+        assert_eq!(played, Move::Other(String::from(val)));
+    }
+
+    #[test_case("a1", Move::Play(Point::new(0, 0)) ; "Upper left")]
+    #[test_case("k11", Move::Play(Point::new(10, 10)) ; "Lower right")]
+    #[test_case("swap-sides", Move::Other(String::from("swap-sides")) ; "Swap")]
+    #[test_case("resign", Move::Other(String::from("resign")) ; "Resign")]
+    fn can_parse_hex_move(val: &str, expected: Move) {
+        let played = Hex.parse_move(val, square(11)).unwrap();
+
+        assert_eq!(played, expected);
+        assert_eq!(Hex.move_to_sgf(&played, square(11)), val);
+    }
+
+    #[test_case("l1" ; "Column outside board")]
+    #[test_case("a12" ; "Row outside board")]
+    #[test_case("a0" ; "Row zero")]
+    #[test_case("a+1" ; "Sign")]
+    fn invalid_hex_point_is_error(val: &str) {
+        assert!(Hex.parse_point(val, square(11)).is_err());
+    }
+
+    #[test]
+    fn can_parse_hex_game() {
+        let content = "(;FF[4]GM[11]SZ[11];B[a1];W[swap-sides];B[resign])";
+        let collection = crate::parse_str(content).unwrap();
+
+        assert_eq!(
+            collection.game_trees()[0]
+                .main_line()
+                .nth(1)
+                .unwrap()
+                .get("B")
+                .unwrap()
+                .values,
+            vec![PropertyValue::Move(Move::Play(Point::new(0, 0)))]
+        );
+        assert_eq!(
+            collection.to_sgf(&WriteOptions::default()),
+            format!("{}\n", content)
+        );
+    }
+
+    #[test]
+    fn backgammon_keeps_moves() {
+        let played = Backgammon.parse_move("61lrrx", square(24)).unwrap();
+
+        assert_eq!(played, Move::Other(String::from("61lrrx")));
+        assert_eq!(
+            Backgammon.parse_point("x", square(24)).unwrap(),
+            Point::new(23, 0)
+        );
+    }
+
+    #[test]
+    fn moves_are_decoded_by_game() {
+        let collection = crate::parse_str("(;GM[3];W[e2e4];B[e7-e5];W[O-O])").unwrap();
+        let moves: Vec<&PropertyValue> = collection.game_trees()[0]
+            .main_line()
+            .skip(1)
+            .map(|node| &node.properties[0].values[0])
+            .collect();
+
+        assert_eq!(
+            moves,
+            vec![
+                &PropertyValue::Move(Move::Step(Point::new(4, 6), Point::new(4, 4))),
+                &PropertyValue::Move(Move::Step(Point::new(4, 1), Point::new(4, 3))),
+                &PropertyValue::Move(Move::Other(String::from("O-O"))),
+            ]
+        );
+    }
+
+    #[test]
+    fn board_size_defaults_by_game() {
+        assert!(crate::parse_str("(;GM[2];B[hh])").is_ok());
+        assert!(crate::parse_str("(;GM[2];AB[ii])").is_err());
+        assert!(crate::parse_str("(;GM[2]SZ[10];AB[ii])").is_ok());
+    }
+
+    #[test]
+    fn unsupported_game_keeps_values() {
+        let collection = crate::parse_str("(;GM[7]AB[e0];B[h9g7])").unwrap();
+        let tree = &collection.game_trees()[0];
+
+        assert_eq!(
+            tree.root().get("AB").unwrap().values,
+            vec![PropertyValue::Unknown(String::from("e0"))]
+        );
+        assert_eq!(
+            *collection.warnings()[0].kind(),
+            ErrorKind::UnsupportedGame(7)
+        );
+        assert_eq!(
+            tree.to_sgf(&WriteOptions::default()),
+            "(;GM[7]AB[e0];B[h9g7])"
+        );
+    }
+
+    #[test]
+    fn moves_are_written_by_game() {
+        let content = "(;GM[5];W[a1];B[a1-d1])";
+        let collection = crate::parse_str(content).unwrap();
+
+        assert_eq!(
+            collection.to_sgf(&WriteOptions::default()),
+            "(;GM[5];W[a1];B[a1d1])\n"
+        );
+    }
+
+    struct Shogi;
+
+    impl GameCodec for Shogi {
+        fn game(&self) -> u32 {
+            100
+        }
+
+        fn default_size(&self) -> BoardSize {
+            square(9)
+        }
+
+        fn parse_point(&self, val: &str, size: BoardSize) -> Result<Point, crate::SgfParseError> {
+            Point::parse(val, size)
+        }
+
+        fn parse_move(&self, val: &str, _: BoardSize) -> Result<Move, crate::SgfParseError> {
+            Ok(Move::Other(val.to_uppercase()))
+        }
+
+        fn point_to_sgf(&self, point: Point, _: BoardSize) -> String {
+            point.to_sgf()
+        }
+
+        fn move_to_sgf(&self, played: &Move, _: BoardSize) -> String {
+            format!("{:?}", played)
+        }
+    }
+
+    #[test]
+    fn can_register_codec() {
+        let mut codecs = Codecs::default();
+        codecs.register(Shogi);
+
+        let codec = codecs.get(100).unwrap();
+        assert_eq!(
+            codec.parse_move("7g7f", square(9)).unwrap(),
+            Move::Other(String::from("7G7F"))
+        );
+        assert_ne!(codecs, Codecs::default());
+    }
+
+    #[test]
+    fn can_parse_with_registered_codec() {
+        let mut options = ParseOptions::default();
+        options.codecs.register(Shogi);
+
+        let collection = crate::parse_str_with("(;GM[100];B[7g7f])", options).unwrap();
+        let played = &collection.game_trees()[0]
+            .main_line()
+            .nth(1)
+            .unwrap()
+            .properties[0];
+
+        assert_eq!(
+            played.values,
+            vec![PropertyValue::Move(Move::Other(String::from("7G7F")))]
+        );
+        assert!(collection.warnings().is_empty());
+    }
+}
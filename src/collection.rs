@@ -1,58 +1,357 @@
 use crate::chars;
-use crate::errors::SgfParseError;
+use crate::charset;
+use crate::errors::{ErrorKind, SgfParseError};
 use crate::game_tree::GameTree;
+use crate::lexer::TokenKind;
+use crate::parser::{ParseOptions, Parser};
+use crate::upgrade;
+use crate::writer::{WriteOptions, Writer};
+use encoding_rs::{Encoding, UTF_8};
+use std::fmt::{Display, Formatter};
 
+#[derive(Debug)]
 pub struct Collection {
     game_trees: Vec<GameTree>,
+    // Problems that didn't stop parsing, e.g. skipped unknown properties.
+    warnings: Vec<SgfParseError>,
+    // Repairs of malformed source made in lenient mode, see `ParseOptions::strict`.
+    recoveries: Vec<SgfParseError>,
+    // The charset the source was read in, and is written back in by `to_bytes`.
+    encoding: &'static Encoding,
 }
 
 impl Collection {
     pub fn new(source: &str) -> Result<Self, SgfParseError> {
-        Ok(Self::parse(source)?)
+        Self::parse(&mut Parser::new(source))
     }
 
-    fn parse(source: &str) -> Result<Self, SgfParseError> {
-        let mut skip_counter = 0;
-        let mut game_trees: Vec<GameTree> = vec![];
+    pub fn with_options(source: &str, options: ParseOptions) -> Result<Self, SgfParseError> {
+        Self::parse(&mut Parser::with_options(source, options))
+    }
+
+    // Reads a source in the charset given by CA in the root node, see `charset`.
+    pub fn from_bytes(source: &[u8]) -> Result<Self, SgfParseError> {
+        Self::from_bytes_with(source, ParseOptions::default())
+    }
+
+    pub fn from_bytes_with(source: &[u8], options: ParseOptions) -> Result<Self, SgfParseError> {
+        let decoded = charset::decode(source);
+
+        let mut parser = Parser::with_options(&decoded.text, options);
+        for warning in decoded.warnings {
+            parser.warn(warning);
+        }
+
+        Ok(Collection {
+            encoding: decoded.encoding,
+            ..Self::parse(&mut parser)?
+        })
+    }
 
-        for (index, character) in source.chars().enumerate() {
-            let index = index + 1;
+    // Parses a source that was read in the given charset, which `to_bytes` writes it back in.
+    pub(crate) fn from_decoded(
+        source: &str,
+        encoding: &'static Encoding,
+    ) -> Result<Self, SgfParseError> {
+        Ok(Collection {
+            encoding,
+            ..Self::new(source)?
+        })
+    }
+
+    pub fn game_trees(&self) -> &[GameTree] {
+        &self.game_trees
+    }
 
-            if skip_counter > 0 {
-                skip_counter -= 1;
+    pub fn warnings(&self) -> &[SgfParseError] {
+        &self.warnings
+    }
+
+    pub fn recoveries(&self) -> &[SgfParseError] {
+        &self.recoveries
+    }
+
+    // Converts games written in FF[1] to FF[3] to FF[4], see `upgrade`.
+    pub fn upgrade(&mut self) {
+        for game_tree in &mut self.game_trees {
+            upgrade::upgrade(game_tree);
+        }
+    }
+
+    pub fn to_sgf(&self, options: &WriteOptions) -> String {
+        let mut writer = Writer::new(options);
+        writer.write_collection(self);
+
+        writer.finish()
+    }
+
+    // Like `to_sgf`, but encoded in the charset the source was read in.
+    pub fn to_bytes(&self, options: &WriteOptions) -> Vec<u8> {
+        charset::encode(&self.to_sgf(options), self.encoding)
+    }
+
+    // The name of the charset the source was read in, e.g. "Shift_JIS". Collections parsed from
+    // a `str` are UTF-8.
+    pub fn charset(&self) -> &'static str {
+        self.encoding.name()
+    }
+
+    fn parse(parser: &mut Parser) -> Result<Self, SgfParseError> {
+        let mut game_trees: Vec<GameTree> = vec![];
+
+        // A Collection is one or more GameTrees; anything else at this level is an error.
+        while let Some(token) = parser.lexer.peek() {
+            let is_tree = matches!(token, Ok(token) if token.kind == TokenKind::TreeStart);
+            if !is_tree && !parser.options.strict {
+                let (start, skipped) = parser.lexer.skip_until(chars::TREE_START);
+                let skipped = String::from(skipped.trim_end());
+                parser.recover(
+                    parser
+                        .lexer
+                        .error_at(ErrorKind::SkippedText(skipped), start),
+                )?;
                 continue;
             }
 
-            match character {
-                chars::TREE_START => {
-                    // We encountered a nested GameTree.
-                    let remaining_content = source.split_at(index);
-                    let leaf_result = GameTree::parse(remaining_content.1)?;
-                    game_trees.push(leaf_result.0);
-                    skip_counter = leaf_result.1
+            if let Ok(token) = *token {
+                if token.kind == TokenKind::TreeEnd {
+                    // A closing parenthesis without a matching opening one.
+                    return Err(parser
+                        .lexer
+                        .error_at(ErrorKind::UnbalancedTree, token.span.start));
                 }
-                // White space (space, tab, carriage return, line feed, vertical tab and so on) may appear
-                // anywhere between PropValues, Properties, Nodes, Sequences and GameTrees.
-                ' ' | '\n' | '\t' => (),
-                _ => todo!(),
             }
+
+            parser.start_game();
+            game_trees.push(GameTree::parse(parser)?);
         }
 
-        Ok(Collection { game_trees })
+        if game_trees.is_empty() {
+            return Err(parser.lexer.error_at(ErrorKind::EmptyCollection, 0));
+        }
+
+        Ok(Collection {
+            game_trees,
+            warnings: parser.take_warnings(),
+            recoveries: parser.take_recoveries(),
+            encoding: UTF_8,
+        })
+    }
+}
+
+impl Display for Collection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_sgf(&WriteOptions::default()))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::Collection;
+    use crate::errors::ErrorKind;
+    use crate::parser::ParseOptions;
+    use crate::point::{Move, Point};
+    use crate::property::PropertyValue;
+    use crate::writer::WriteOptions;
+    use test_case::test_case;
+
+    fn lenient(content: &str) -> Collection {
+        let options = ParseOptions {
+            strict: false,
+            ..ParseOptions::default()
+        };
+
+        Collection::with_options(content, options).unwrap()
+    }
 
     #[test]
     fn can_parse_multiple_game_trees() {
-        let content = "( ab ) ( cd )";
+        let content = "(;FF[4]) (;FF[3])";
         let collection = Collection::new(content).unwrap();
 
         assert_eq!(collection.game_trees.len(), 2);
-        // assert_eq!(collection.game_trees.get(0).unwrap().content, "ab");
-        // assert_eq!(collection.game_trees.get(1).unwrap().content, "cd");
+    }
+
+    #[test]
+    fn collects_warnings_with_location() {
+        let content = "(;FF[4]GM[1]\n;B[pd]KGS[x];W[dp]FOXTAG[1])";
+        let collection = Collection::new(content).unwrap();
+
+        let warnings = collection.warnings();
+        assert_eq!(warnings.len(), 2);
+
+        assert_eq!(*warnings[0].kind(), ErrorKind::UnknownProperty);
+        assert_eq!(warnings[0].property(), Some("KGS"));
+        assert_eq!(warnings[0].node(), Some(1));
+        assert_eq!(warnings[0].position().unwrap().line, 2);
+        assert_eq!(warnings[0].position().unwrap().column, 7);
+
+        assert_eq!(warnings[1].property(), Some("FOXTAG"));
+        assert_eq!(warnings[1].node(), Some(2));
+    }
+
+    #[test]
+    fn empty_collection_is_error() {
+        let error = Collection::new(" \n").err().unwrap();
+
+        assert_eq!(*error.kind(), ErrorKind::EmptyCollection);
+    }
+
+    #[test]
+    fn stray_content_is_error() {
+        let error = Collection::new("(;FF[4]) ab").err().unwrap();
+
+        assert_eq!(*error.kind(), ErrorKind::UnexpectedCharacter('a'));
+        assert_eq!(error.position().unwrap().column, 10);
+    }
+
+    #[test]
+    fn unbalanced_closing_parenthesis_is_error() {
+        let error = Collection::new("(;FF[4]))").err().unwrap();
+
+        assert_eq!(*error.kind(), ErrorKind::UnbalancedTree);
+        assert_eq!(error.position().unwrap().offset, 8);
+    }
+
+    #[test]
+    fn error_reports_line_and_column() {
+        let content = "(;FF[4]\n;N[本因坊:1]\n;FF[x])";
+        let error = Collection::new(content).err().unwrap();
+
+        let position = error.position().unwrap();
+        assert_eq!(position.line, 3);
+        assert_eq!(position.column, 4);
+        assert_eq!(error.property(), Some("FF"));
+        assert_eq!(error.node(), Some(2));
+    }
+
+    #[test]
+    fn bytes_are_written_back_in_their_charset() {
+        let source = encoding_rs::SHIFT_JIS
+            .encode("(;FF[4]CA[Shift_JIS]PB[本因坊秀策]C[ソ])")
+            .0
+            .into_owned();
+        let collection = Collection::from_bytes(&source).unwrap();
+
+        assert_eq!(collection.charset(), "Shift_JIS");
+        let black = collection.game_trees()[0].root().get("PB").unwrap();
+        assert_eq!(
+            black.values,
+            vec![PropertyValue::SimpleText(String::from("本因坊秀策"))]
+        );
+
+        let options = WriteOptions {
+            line_width: None,
+            ..WriteOptions::default()
+        };
+        let mut expected = source.clone();
+        expected.push(b'\n');
+        assert_eq!(collection.to_bytes(&options), expected);
+    }
+
+    #[test_case("Received: from igs\n(;FF[4];B[aa])", ErrorKind::SkippedText(String::from("Received: from igs")) ; "Text before tree")]
+    #[test_case("(;FF[4];B[aa]) -- end of file", ErrorKind::SkippedText(String::from("-- end of file")) ; "Text after tree")]
+    #[test_case("(;FF[4];B[aa]))", ErrorKind::SkippedText(String::from(")")) ; "Stray closing parenthesis")]
+    #[test_case("(;FF[4];B[aa]", ErrorKind::UnbalancedTree ; "Unclosed tree")]
+    #[test_case("(;FF[4];Black[aa])", ErrorKind::LowercaseIdentifier(String::from("Black")) ; "Lowercase identifier")]
+    #[test_case("(;FF[4]C[see [1]];B[aa])", ErrorKind::UnescapedBracket ; "Unescaped bracket")]
+    #[test_case("(;FF[4];B[aa] % ;W[bb])", ErrorKind::SkippedText(String::from("%")) ; "Stray character")]
+    fn lenient_mode_recovers(content: &str, expected: ErrorKind) {
+        let collection = lenient(content);
+
+        assert_eq!(collection.game_trees().len(), 1);
+        let kinds: Vec<&ErrorKind> = collection.recoveries().iter().map(|r| r.kind()).collect();
+        assert_eq!(kinds, vec![&expected]);
+
+        assert!(Collection::new(content).is_err());
+    }
+
+    #[test_case("(;FF[3]GaMe[1];AddBlack[aa])", "AB" ; "FF3")]
+    #[test_case("(;Black[aa];White[bb])", "W" ; "Without FF")]
+    fn lowercase_identifiers_are_valid_before_ff4(content: &str, id: &str) {
+        let collection = Collection::new(content).unwrap();
+        let tree = &collection.game_trees()[0];
+
+        assert!(tree.main_line().any(|node| node.get(id).is_some()));
+        assert!(collection.recoveries().is_empty());
+        assert!(collection.warnings().is_empty());
+    }
+
+    #[test]
+    fn lenient_mode_keeps_repaired_values() {
+        let collection = lenient("x(;FF[4]C[see [1]];AddBlack[aa];Black[bb](;W[cc]");
+        let tree = &collection.game_trees()[0];
+
+        assert_eq!(
+            tree.root().get("C").unwrap().values,
+            vec![PropertyValue::Text(String::from("see [1]"))]
+        );
+        assert!(tree.node_at(&[0]).unwrap().get("AB").is_some());
+        assert_eq!(
+            tree.node_at(&[0, 0]).unwrap().get("B").unwrap().values,
+            vec![PropertyValue::Move(Move::Play(Point::new(1, 1)))]
+        );
+        assert_eq!(collection.recoveries().len(), 6);
+
+        let options = WriteOptions {
+            line_width: None,
+            ..WriteOptions::default()
+        };
+        assert_eq!(
+            collection.to_sgf(&options),
+            "(;FF[4]C[see [1\\]];AB[aa];B[bb](;W[cc]))\n"
+        );
+    }
+
+    #[test]
+    fn lenient_mode_closes_truncated_value() {
+        let collection = lenient("(;FF[4]C[oops");
+        let kinds: Vec<&ErrorKind> = collection.recoveries().iter().map(|r| r.kind()).collect();
+
+        assert_eq!(
+            collection.game_trees()[0].root().get("C").unwrap().values,
+            vec![PropertyValue::Text(String::from("oops"))]
+        );
+        assert_eq!(
+            kinds,
+            vec![&ErrorKind::UnbalancedTree, &ErrorKind::UnterminatedValue]
+        );
+        assert!(Collection::new("(;FF[4]C[oops").is_err());
+    }
+
+    #[test_case("(;FF[4]HA[0])", "HA", "0" ; "Number out of range")]
+    #[test_case("(;FF[4]KM[six])", "KM", "six" ; "Real")]
+    #[test_case("(;FF[4]SZ[19];B[zz])", "B", "zz" ; "Move outside board")]
+    #[test_case("(;FF[x])", "FF", "x" ; "Root property")]
+    fn lenient_mode_keeps_invalid_values(content: &str, id: &str, raw: &str) {
+        let collection = lenient(content);
+        let tree = &collection.game_trees()[0];
+        let node = tree.main_line().last().unwrap();
+
+        assert_eq!(
+            node.get(id).unwrap().values,
+            vec![PropertyValue::Unknown(String::from(raw))]
+        );
+        assert_eq!(collection.warnings().len(), 1);
+        assert_eq!(collection.warnings()[0].property(), Some(id));
+        assert!(matches!(
+            collection.warnings()[0].kind(),
+            ErrorKind::InvalidValue(_)
+        ));
+
+        assert!(Collection::new(content).is_err());
+    }
+
+    #[test]
+    fn recoveries_are_in_source_order() {
+        let collection = lenient("junk(;FF[4];Black[aa]");
+        let offsets: Vec<usize> = collection
+            .recoveries()
+            .iter()
+            .map(|repair| repair.position().unwrap().offset)
+            .collect();
+
+        assert_eq!(offsets, vec![0, 4, 12]);
+        assert_eq!(collection.recoveries()[2].node(), Some(1));
     }
 }
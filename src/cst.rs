@@ -0,0 +1,538 @@
+// A concrete syntax tree keeps a Collection exactly as it is written: the white space between
+// tokens, the order of properties and the raw text of every value, escapes included. Writing it
+// back gives the source it was parsed from, so edits only change the text they touch.
+
+// The CST only checks the structure of the source, i.e. GameTrees, Nodes and Properties; the
+// values are checked when it's turned into a `Collection`.
+
+// Sources read as bytes are decoded in the charset given by CA in the root node, see `charset`,
+// and written back in it by `to_bytes`. Sources that don't survive decoding unchanged, e.g. with
+// bytes that are invalid in their charset, are an error.
+
+use crate::charset;
+use crate::codec::GameCodec;
+use crate::collection::Collection;
+use crate::errors::{ErrorKind, SgfParseError};
+use crate::lexer::{Lexer, Token, TokenKind};
+use crate::point::BoardSize;
+use crate::property::PropertyValue;
+use encoding_rs::{Encoding, UTF_8};
+
+// A token as written in the source, with the white space before it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Piece {
+    trivia: String,
+    text: String,
+}
+
+impl Piece {
+    fn new(text: String) -> Self {
+        Piece {
+            trivia: String::new(),
+            text,
+        }
+    }
+
+    fn write(&self, output: &mut String) {
+        output.push_str(&self.trivia);
+        output.push_str(&self.text);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cst {
+    trees: Vec<CstTree>,
+    // White space after the last GameTree.
+    trailing: String,
+    // The charset the source was read in, and is written back in by `to_bytes`.
+    encoding: &'static Encoding,
+    // The byte order mark the source started with, if any. It isn't part of the text.
+    bom: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CstTree {
+    open: Piece,
+    nodes: Vec<CstNode>,
+    leaves: Vec<CstTree>,
+    close: Piece,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CstNode {
+    start: Piece,
+    properties: Vec<CstProperty>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CstProperty {
+    ident: Piece,
+    // Each value with its brackets, e.g. "[pd]".
+    values: Vec<Piece>,
+}
+
+impl Cst {
+    pub fn parse(source: &str) -> Result<Self, SgfParseError> {
+        let mut builder = Builder {
+            lexer: Lexer::new(source),
+            source,
+            end: 0,
+        };
+        let mut trees = vec![];
+
+        while builder.lexer.peek().is_some() {
+            trees.push(builder.tree()?);
+        }
+        if trees.is_empty() {
+            return Err(builder.lexer.error_at(ErrorKind::EmptyCollection, 0));
+        }
+
+        Ok(Cst {
+            trees,
+            trailing: String::from(&source[builder.end..]),
+            encoding: UTF_8,
+            bom: vec![],
+        })
+    }
+
+    pub fn from_bytes(source: &[u8]) -> Result<Self, SgfParseError> {
+        let decoded = charset::decode(source);
+        // Decoding drops the byte order mark, so it's kept aside.
+        let bom = Encoding::for_bom(source).map_or(0, |(_, length)| length);
+        if charset::encode(&decoded.text, decoded.encoding) != source[bom..] {
+            return Err(SgfParseError::new(ErrorKind::Charset(format!(
+                "Source can't be read as {} without changing it",
+                decoded.encoding.name()
+            ))));
+        }
+
+        Ok(Cst {
+            encoding: decoded.encoding,
+            bom: source[..bom].to_vec(),
+            ..Cst::parse(&decoded.text)?
+        })
+    }
+
+    // The name of the charset the source was read in, see `Collection::charset`.
+    pub fn charset(&self) -> &'static str {
+        self.encoding.name()
+    }
+
+    pub fn trees(&self) -> &[CstTree] {
+        &self.trees
+    }
+
+    // The node at `path` in the `tree`th GameTree, see `GameTree::node_at`.
+    pub fn node(&self, tree: usize, path: &[usize]) -> Option<&CstNode> {
+        let mut tree = self.trees.get(tree)?;
+        let mut index = 0;
+
+        for step in path {
+            if index + 1 < tree.nodes.len() {
+                if *step != 0 {
+                    return None;
+                }
+                index += 1;
+            } else {
+                tree = tree.leaves.get(*step)?;
+                index = 0;
+            }
+        }
+
+        tree.nodes.get(index)
+    }
+
+    pub fn node_mut(&mut self, tree: usize, path: &[usize]) -> Option<&mut CstNode> {
+        let mut tree = self.trees.get_mut(tree)?;
+        let mut index = 0;
+
+        for step in path {
+            if index + 1 < tree.nodes.len() {
+                if *step != 0 {
+                    return None;
+                }
+                index += 1;
+            } else {
+                tree = tree.leaves.get_mut(*step)?;
+                index = 0;
+            }
+        }
+
+        tree.nodes.get_mut(index)
+    }
+
+    // The source as it was parsed, with the edits applied.
+    pub fn to_sgf(&self) -> String {
+        let mut output = String::new();
+
+        for tree in &self.trees {
+            tree.write(&mut output);
+        }
+        output.push_str(&self.trailing);
+
+        output
+    }
+
+    // Like `to_sgf`, but encoded in the charset the source was read in.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.bom.clone();
+        bytes.extend(charset::encode(&self.to_sgf(), self.encoding));
+
+        bytes
+    }
+
+    // Parses the edited source into a `Collection`, checking every value.
+    pub fn collection(&self) -> Result<Collection, SgfParseError> {
+        Collection::from_decoded(&self.to_sgf(), self.encoding)
+    }
+}
+
+impl CstTree {
+    pub fn nodes(&self) -> &[CstNode] {
+        &self.nodes
+    }
+
+    pub fn leaves(&self) -> &[CstTree] {
+        &self.leaves
+    }
+
+    fn write(&self, output: &mut String) {
+        self.open.write(output);
+        for node in &self.nodes {
+            node.write(output);
+        }
+        for leaf in &self.leaves {
+            leaf.write(output);
+        }
+        self.close.write(output);
+    }
+}
+
+impl CstNode {
+    pub fn properties(&self) -> &[CstProperty] {
+        &self.properties
+    }
+
+    pub fn get(&self, id: &str) -> Option<&CstProperty> {
+        self.properties.iter().find(|prop| prop.id() == id)
+    }
+
+    // Replaces the values of a property, keeping its place in the node and the white space
+    // around it; a new property is added at the end of the node.
+    pub fn set(
+        &mut self,
+        id: &str,
+        values: &[PropertyValue],
+        codec: &dyn GameCodec,
+        size: BoardSize,
+    ) {
+        let raw: Vec<String> = values
+            .iter()
+            .map(|value| value.to_sgf_with(codec, size))
+            .collect();
+
+        self.set_raw(id, &raw.iter().map(String::as_str).collect::<Vec<&str>>());
+    }
+
+    // Like `set`, but with values as they are written between the brackets, escapes included.
+    pub fn set_raw(&mut self, id: &str, values: &[&str]) {
+        let mut texts: Vec<String> = values.iter().map(|value| format!("[{}]", value)).collect();
+        // An empty `elist` is written as "[]".
+        if texts.is_empty() {
+            texts.push(String::from("[]"));
+        }
+
+        match self.properties.iter_mut().find(|prop| prop.id() == id) {
+            Some(prop) => {
+                let mut trivia = prop.values.iter().map(|value| value.trivia.clone());
+                prop.values = texts
+                    .into_iter()
+                    .map(|text| Piece {
+                        trivia: trivia.next().unwrap_or_default(),
+                        text,
+                    })
+                    .collect();
+            }
+            None => self.properties.push(CstProperty {
+                ident: Piece::new(String::from(id)),
+                values: texts.into_iter().map(Piece::new).collect(),
+            }),
+        }
+    }
+
+    // Removes a property along with the white space before it. Returns whether it was there.
+    pub fn remove(&mut self, id: &str) -> bool {
+        let count = self.properties.len();
+        self.properties.retain(|prop| prop.id() != id);
+
+        self.properties.len() != count
+    }
+
+    fn write(&self, output: &mut String) {
+        self.start.write(output);
+        for property in &self.properties {
+            property.ident.write(output);
+            for value in &property.values {
+                value.write(output);
+            }
+        }
+    }
+}
+
+impl CstProperty {
+    pub fn id(&self) -> &str {
+        &self.ident.text
+    }
+
+    // The values as they are written between the brackets, escapes included.
+    pub fn raw_values(&self) -> Vec<&str> {
+        self.values
+            .iter()
+            .map(|value| &value.text[1..value.text.len() - 1])
+            .collect()
+    }
+}
+
+struct Builder<'a> {
+    lexer: Lexer<'a>,
+    source: &'a str,
+    // Where the last token taken ends.
+    end: usize,
+}
+
+impl Builder<'_> {
+    fn take(&mut self, kind: TokenKind) -> Result<Piece, SgfParseError> {
+        let token: Token = self.lexer.expect(kind)?;
+        let piece = Piece {
+            trivia: String::from(&self.source[self.end..token.span.start]),
+            text: String::from(&self.source[token.span.start..token.span.end]),
+        };
+        self.end = token.span.end;
+
+        Ok(piece)
+    }
+
+    fn tree(&mut self) -> Result<CstTree, SgfParseError> {
+        let open = self.take(TokenKind::TreeStart)?;
+
+        let mut nodes = vec![];
+        while self.lexer.peek_kind() == Some(TokenKind::NodeStart) {
+            nodes.push(self.node()?);
+        }
+        if nodes.is_empty() {
+            return Err(self
+                .lexer
+                .error_at(ErrorKind::EmptyGameTree, self.end - open.text.len()));
+        }
+
+        let mut leaves = vec![];
+        while self.lexer.peek_kind() == Some(TokenKind::TreeStart) {
+            leaves.push(self.tree()?);
+        }
+
+        Ok(CstTree {
+            open,
+            nodes,
+            leaves,
+            close: self.take(TokenKind::TreeEnd)?,
+        })
+    }
+
+    fn node(&mut self) -> Result<CstNode, SgfParseError> {
+        let start = self.take(TokenKind::NodeStart)?;
+
+        let mut properties = vec![];
+        while self.lexer.peek_kind() == Some(TokenKind::PropIdent) {
+            let ident = self.take(TokenKind::PropIdent)?;
+            let mut values = vec![self.take(TokenKind::PropValue)?];
+            while self.lexer.peek_kind() == Some(TokenKind::PropValue) {
+                values.push(self.take(TokenKind::PropValue)?);
+            }
+
+            properties.push(CstProperty { ident, values });
+        }
+
+        Ok(CstNode { start, properties })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Cst;
+    use crate::codec::{Algebraic, Go, CHESS};
+    use crate::errors::ErrorKind;
+    use crate::point::{BoardSize, Move, Point};
+    use crate::property::PropertyValue;
+    use std::fs;
+    use test_case::test_case;
+
+    #[test]
+    fn example_file_round_trips_exactly() {
+        let source = fs::read_to_string("resources/example.sgf").unwrap();
+
+        assert_eq!(Cst::parse(&source).unwrap().to_sgf(), source);
+    }
+
+    #[test_case("(;FF[4]C[a\\:b] ;B[pd]\n)\n" ; "Escapes and white space")]
+    #[test_case("  (;FF[4])\r\n(;FF[4] (;B[aa])(;W[bb]))" ; "Several trees")]
+    #[test_case("(;AB [aa]\n  [bb])" ; "White space between values")]
+    fn round_trips_exactly(source: &str) {
+        assert_eq!(Cst::parse(source).unwrap().to_sgf(), source);
+    }
+
+    #[test]
+    fn changing_comment_only_changes_its_value() {
+        let source = "(;FF[4]\n  GM[1]  C[old\\] text]\n;B[pd])";
+        let mut cst = Cst::parse(source).unwrap();
+
+        let root = cst.node_mut(0, &[]).unwrap();
+        root.set(
+            "C",
+            &[PropertyValue::Text(String::from("new]"))],
+            &Go,
+            BoardSize::default(),
+        );
+
+        assert_eq!(cst.to_sgf(), "(;FF[4]\n  GM[1]  C[new\\]]\n;B[pd])");
+    }
+
+    #[test]
+    fn bytes_round_trip_in_their_charset() {
+        let source = encoding_rs::SHIFT_JIS
+            .encode("(;CA[Shift_JIS]PB[本因坊秀策]\n  C[ソ])")
+            .0
+            .into_owned();
+        let mut cst = Cst::from_bytes(&source).unwrap();
+
+        assert_eq!(cst.charset(), "Shift_JIS");
+        assert_eq!(cst.to_bytes(), source);
+
+        cst.node_mut(0, &[]).unwrap().set_raw("PW", &["秀和"]);
+        let edited = "(;CA[Shift_JIS]PB[本因坊秀策]\n  C[ソ]PW[秀和])";
+        assert_eq!(
+            cst.to_bytes(),
+            encoding_rs::SHIFT_JIS.encode(edited).0.as_ref()
+        );
+        assert_eq!(cst.collection().unwrap().charset(), "Shift_JIS");
+    }
+
+    #[test]
+    fn byte_order_mark_is_kept() {
+        let source = fs::read("resources/bom.sgf").unwrap();
+        let cst = Cst::from_bytes(&source).unwrap();
+
+        assert_eq!(cst.charset(), "UTF-8");
+        assert!(cst.to_sgf().starts_with("(;FF[4]"));
+        assert_eq!(cst.to_bytes(), source);
+    }
+
+    #[test]
+    fn bytes_invalid_in_charset_are_error() {
+        let error = Cst::from_bytes(b"(;CA[UTF-8]C[\xff])").unwrap_err();
+
+        assert!(matches!(error.kind(), ErrorKind::Charset(_)));
+    }
+
+    #[test]
+    fn keeps_raw_values() {
+        let cst = Cst::parse("(;C[soft\\\nbreak]AP[Tool\\:X:1])").unwrap();
+        let root = cst.node(0, &[]).unwrap();
+
+        assert_eq!(root.get("C").unwrap().raw_values(), vec!["soft\\\nbreak"]);
+        assert_eq!(root.get("AP").unwrap().raw_values(), vec!["Tool\\:X:1"]);
+    }
+
+    #[test]
+    fn can_add_and_remove_properties() {
+        let mut cst = Cst::parse("(;FF[4] GM[1];B[pd] C[x];W[dp])").unwrap();
+
+        let node = cst.node_mut(0, &[0]).unwrap();
+        assert!(node.remove("C"));
+        assert!(!node.remove("C"));
+        node.set(
+            "W",
+            &[PropertyValue::Move(Move::Play(Point::new(3, 3)))],
+            &Go,
+            BoardSize::default(),
+        );
+
+        assert_eq!(cst.to_sgf(), "(;FF[4] GM[1];B[pd]W[dd];W[dp])");
+    }
+
+    #[test]
+    fn moves_are_set_in_notation_of_game() {
+        let mut cst = Cst::parse("(;GM[3];W[e2e4])").unwrap();
+        let size = BoardSize {
+            width: 8,
+            height: 8,
+        };
+
+        cst.node_mut(0, &[0]).unwrap().set(
+            "W",
+            &[PropertyValue::Move(Move::Step(
+                Point::new(3, 6),
+                Point::new(3, 4),
+            ))],
+            &Algebraic::new(CHESS, 8),
+            size,
+        );
+        assert_eq!(cst.to_sgf(), "(;GM[3];W[d2d4])");
+    }
+
+    #[test]
+    fn changing_value_count_keeps_layout() {
+        let mut cst = Cst::parse("(;AB[aa]\n  [bb]\n  [cc]SZ[9])").unwrap();
+
+        cst.node_mut(0, &[]).unwrap().set_raw("AB", &["dd", "ee"]);
+        assert_eq!(cst.to_sgf(), "(;AB[dd]\n  [ee]SZ[9])");
+
+        cst.node_mut(0, &[])
+            .unwrap()
+            .set_raw("AB", &["dd", "ee", "ff"]);
+        assert_eq!(cst.to_sgf(), "(;AB[dd]\n  [ee][ff]SZ[9])");
+    }
+
+    #[test]
+    fn nodes_follow_paths() {
+        let cst = Cst::parse("(;C[root];C[a](;C[b];C[c])(;C[d]))").unwrap();
+        let comment = |path: &[usize]| {
+            cst.node(0, path)
+                .map(|node| node.get("C").unwrap().raw_values()[0])
+        };
+
+        assert_eq!(comment(&[]), Some("root"));
+        assert_eq!(comment(&[0, 0, 0]), Some("c"));
+        assert_eq!(comment(&[0, 1]), Some("d"));
+        assert_eq!(comment(&[1]), None);
+        assert_eq!(comment(&[0, 2]), None);
+    }
+
+    #[test]
+    fn edited_source_parses_into_collection() {
+        let mut cst = Cst::parse("(;FF[4]SZ[9];B[ee])").unwrap();
+        cst.node_mut(0, &[0]).unwrap().set_raw("B", &["zz"]);
+
+        let error = cst.collection().err().unwrap();
+        assert!(matches!(error.kind(), ErrorKind::InvalidValue(_)));
+
+        cst.node_mut(0, &[0]).unwrap().set_raw("B", &["ff"]);
+        let collection = cst.collection().unwrap();
+        assert_eq!(
+            collection.game_trees()[0]
+                .node_at(&[0])
+                .unwrap()
+                .get("B")
+                .unwrap()
+                .values,
+            vec![PropertyValue::Move(Move::Play(Point::new(5, 5)))]
+        );
+    }
+
+    #[test_case("(;FF[4]" ; "Unclosed tree")]
+    #[test_case("()" ; "Empty tree")]
+    #[test_case("" ; "Empty")]
+    #[test_case("(;FF[4]) ;" ; "Stray node")]
+    fn malformed_structure_is_error(source: &str) {
+        assert!(Cst::parse(source).is_err());
+    }
+}
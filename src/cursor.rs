@@ -0,0 +1,304 @@
+// Steps through a GameTree the way a board viewer does, without cloning the tree.
+
+use crate::game_tree::GameTree;
+use crate::navigation::Location;
+use crate::node::Node;
+use crate::property::{Property, PropertyValue};
+
+pub struct Cursor<'a> {
+    tree: &'a GameTree,
+    // The locations from the root to the current node, both included.
+    trail: Vec<Location<'a>>,
+}
+
+impl<'a> Cursor<'a> {
+    pub fn new(tree: &'a GameTree) -> Self {
+        Cursor {
+            tree,
+            trail: vec![Location::root(tree)],
+        }
+    }
+
+    pub fn node(&self) -> &'a Node {
+        self.current().node()
+    }
+
+    pub fn properties(&self) -> &'a [Property] {
+        &self.node().properties
+    }
+
+    // The number of nodes between the root and the current node; zero at the root.
+    pub fn depth(&self) -> usize {
+        self.trail.len() - 1
+    }
+
+    // The child indices leading from the root to the current node, see `GameTree::node_at`.
+    pub fn path(&self) -> Vec<usize> {
+        self.trail
+            .windows(2)
+            .map(|pair| self.sibling_index(pair[0], pair[1]))
+            .collect()
+    }
+
+    // The number of the last move played up to the current node. Every B or W counts as a move,
+    // unless MN sets the number explicitly.
+    pub fn move_number(&self) -> u32 {
+        let mut number = 0;
+
+        for location in &self.trail {
+            let properties = &location.node().properties;
+            let has_move = properties
+                .iter()
+                .any(|prop| prop.id == "B" || prop.id == "W");
+            let set_number = properties
+                .iter()
+                .find(|prop| prop.id == "MN")
+                .and_then(|prop| match prop.values.first() {
+                    Some(PropertyValue::Number(number, _, _)) => Some(*number),
+                    _ => None,
+                });
+
+            match set_number {
+                Some(set_number) => number = set_number,
+                None if has_move => number += 1,
+                None => (),
+            }
+        }
+
+        number
+    }
+
+    // The number of nodes that directly follow the current node.
+    pub fn variation_count(&self) -> usize {
+        self.current().children().len()
+    }
+
+    // Moves to the first child, i.e. along the main line. Returns false at the end of a variation.
+    // Cursor isn't an Iterator: it can move both ways, and stepping doesn't yield anything.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> bool {
+        self.enter(0)
+    }
+
+    // Moves to the child with the given index; useful to step into a variation at a fork.
+    pub fn enter(&mut self, index: usize) -> bool {
+        match self.current().child(index) {
+            Some(child) => {
+                self.trail.push(child);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn prev(&mut self) -> bool {
+        if self.trail.len() == 1 {
+            return false;
+        }
+
+        self.trail.pop();
+        true
+    }
+
+    // Moves to the same depth in the next variation of the last fork.
+    pub fn next_variation(&mut self) -> bool {
+        self.switch_variation(1)
+    }
+
+    pub fn prev_variation(&mut self) -> bool {
+        self.switch_variation(-1)
+    }
+
+    // Moves back to the closest node before the current one where the game forks.
+    pub fn up_to_fork(&mut self) -> bool {
+        let fork = (0..self.trail.len() - 1)
+            .rev()
+            .find(|index| self.trail[*index].children().len() > 1);
+
+        match fork {
+            Some(index) => {
+                self.trail.truncate(index + 1);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn goto_root(&mut self) {
+        self.trail.truncate(1);
+    }
+
+    // Moves to the node at `path`; the cursor stays where it is if the path doesn't exist.
+    pub fn goto_path(&mut self, path: &[usize]) -> bool {
+        let mut trail = vec![Location::root(self.tree)];
+
+        for index in path {
+            match trail.last().unwrap().child(*index) {
+                Some(child) => trail.push(child),
+                None => return false,
+            }
+        }
+
+        self.trail = trail;
+        true
+    }
+
+    fn current(&self) -> Location<'a> {
+        *self.trail.last().unwrap()
+    }
+
+    fn sibling_index(&self, parent: Location<'a>, child: Location<'a>) -> usize {
+        parent
+            .children()
+            .iter()
+            .position(|sibling| sibling.is(child.node()))
+            .unwrap()
+    }
+
+    fn switch_variation(&mut self, offset: isize) -> bool {
+        // The variation is chosen at the last fork, the current node's depth is kept.
+        let fork = match (0..self.trail.len() - 1)
+            .rev()
+            .find(|index| self.trail[*index].children().len() > 1)
+        {
+            Some(fork) => fork,
+            None => return false,
+        };
+
+        let depth = self.trail.len();
+        let siblings = self.trail[fork].children();
+        let current = self.sibling_index(self.trail[fork], self.trail[fork + 1]);
+
+        let target = match current.checked_add_signed(offset) {
+            Some(target) if target < siblings.len() => target,
+            _ => return false,
+        };
+
+        self.trail.truncate(fork + 1);
+        self.trail.push(siblings[target]);
+        while self.trail.len() < depth && self.next() {}
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Cursor;
+    use crate::game_tree::GameTree;
+    use crate::parser::Parser;
+    use crate::property::PropertyValue;
+
+    // The example from the node numbering comment in `node.rs`.
+    const NUMBERED: &str =
+        "(;N[root](;N[a];N[b](;N[c])(;N[d];N[e]))(;N[f](;N[g];N[h];N[i])(;N[j])))";
+
+    fn parse(content: &str) -> GameTree {
+        GameTree::parse(&mut Parser::new(content)).unwrap()
+    }
+
+    fn name(cursor: &Cursor) -> String {
+        match &cursor.properties()[0].values[0] {
+            PropertyValue::SimpleText(name) => name.clone(),
+            other => panic!("Expected a name, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn can_step_forward_and_back() {
+        let tree = parse(NUMBERED);
+        let mut cursor = Cursor::new(&tree);
+
+        assert_eq!(name(&cursor), "root");
+        assert!(!cursor.prev());
+
+        assert!(cursor.next());
+        assert!(cursor.next());
+        assert!(cursor.next());
+        assert_eq!(name(&cursor), "c");
+        assert_eq!(cursor.depth(), 3);
+        assert!(!cursor.next());
+
+        assert!(cursor.prev());
+        assert_eq!(name(&cursor), "b");
+    }
+
+    #[test]
+    fn can_switch_variations() {
+        let tree = parse(NUMBERED);
+        let mut cursor = Cursor::new(&tree);
+        cursor.next();
+
+        assert_eq!(name(&cursor), "a");
+        assert!(!cursor.prev_variation());
+        assert!(cursor.next_variation());
+        assert_eq!(name(&cursor), "f");
+        assert!(!cursor.next_variation());
+
+        // The depth is kept when switching further down a variation.
+        cursor.goto_path(&[1, 0, 0]);
+        assert_eq!(name(&cursor), "h");
+        assert!(cursor.next_variation());
+        assert_eq!(name(&cursor), "j");
+        assert_eq!(cursor.path(), vec![1, 1]);
+    }
+
+    #[test]
+    fn can_enter_variation_and_return_to_fork() {
+        let tree = parse(NUMBERED);
+        let mut cursor = Cursor::new(&tree);
+        cursor.goto_path(&[0, 0]);
+
+        assert_eq!(cursor.variation_count(), 2);
+        assert!(cursor.enter(1));
+        assert!(cursor.next());
+        assert_eq!(name(&cursor), "e");
+
+        assert!(cursor.up_to_fork());
+        assert_eq!(name(&cursor), "b");
+        assert!(cursor.up_to_fork());
+        assert_eq!(name(&cursor), "root");
+        assert!(!cursor.up_to_fork());
+    }
+
+    #[test]
+    fn can_goto_path_and_root() {
+        let tree = parse(NUMBERED);
+        let mut cursor = Cursor::new(&tree);
+
+        assert!(cursor.goto_path(&[1, 0, 0, 0]));
+        assert_eq!(name(&cursor), "i");
+        assert_eq!(cursor.path(), vec![1, 0, 0, 0]);
+
+        assert!(!cursor.goto_path(&[2]));
+        assert_eq!(name(&cursor), "i");
+
+        cursor.goto_root();
+        assert_eq!(name(&cursor), "root");
+        assert!(cursor.path().is_empty());
+    }
+
+    #[test]
+    fn move_number_follows_mn() {
+        let collection = crate::parse_file("resources/example.sgf").unwrap();
+        let time_limits = &collection.game_trees()[0];
+        let mut cursor = Cursor::new(time_limits);
+
+        cursor.enter(4);
+        assert_eq!(cursor.move_number(), 1);
+
+        // B[qr] W[rr] B[rq] W[qq] B[sr] W[qs] B[rs] W[ps] B[pr], then W[or]MN[2]
+        for _ in 0..8 {
+            cursor.next();
+        }
+        assert_eq!(cursor.move_number(), 9);
+        cursor.next();
+        assert_eq!(cursor.move_number(), 2);
+        cursor.next();
+        assert_eq!(cursor.move_number(), 3);
+
+        // MN[112]W[pq]
+        cursor.next();
+        assert_eq!(cursor.move_number(), 112);
+    }
+}
@@ -0,0 +1,233 @@
+// From the SGF spec (https://www.red-bean.com/sgf/properties.html#DT):
+// It is MANDATORY to use the ISO-standard format for DT: "YYYY-MM-DD". Partial dates are allowed:
+// "YYYY" - game was played in YYYY, "YYYY-MM" - game was played in YYYY, month MM.
+
+// For games that last more than one day: separate other dates by "," (no space); following
+// shortcuts may be used:
+// "MM-DD" - if preceded by YYYY-MM-DD, MM-DD, MM or DD
+// "MM" - if preceded by YYYY-MM or MM
+// "DD" - if preceded by YYYY-MM-DD, MM-DD or DD
+// Shortcuts acquire the last preceding YYYY and MM (if necessary).
+// Examples: 1996-05,06 = played in May, June 1996; 1996-05-06,07,08 = played on 6th, 7th, 8th May
+// 1996; 1996-12-27,28,1997-01-03,04 = played on 27th, 28th of December 1996 and on 3rd, 4th
+// January 1997.
+
+// It's recommended to use shortcuts whenever possible, so `format_dates` always does.
+
+use crate::errors::{ErrorKind, SgfParseError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PartialDate {
+    Year(u16),
+    Month(u16, u8),
+    Day(u16, u8, u8),
+}
+
+impl PartialDate {
+    pub fn year(&self) -> u16 {
+        match self {
+            PartialDate::Year(year)
+            | PartialDate::Month(year, _)
+            | PartialDate::Day(year, _, _) => *year,
+        }
+    }
+
+    pub fn month(&self) -> Option<u8> {
+        match self {
+            PartialDate::Year(_) => None,
+            PartialDate::Month(_, month) | PartialDate::Day(_, month, _) => Some(*month),
+        }
+    }
+
+    pub fn day(&self) -> Option<u8> {
+        match self {
+            PartialDate::Day(_, _, day) => Some(*day),
+            _ => None,
+        }
+    }
+
+    // The date written out in full, e.g. "1996-05-06".
+    pub fn to_sgf(&self) -> String {
+        match self {
+            PartialDate::Year(year) => format!("{:04}", year),
+            PartialDate::Month(year, month) => format!("{:04}-{:02}", year, month),
+            PartialDate::Day(year, month, day) => format!("{:04}-{:02}-{:02}", year, month, day),
+        }
+    }
+
+    fn validate(self) -> Result<Self, SgfParseError> {
+        if let Some(month) = self.month() {
+            if !(1..=12).contains(&month) {
+                return Err(invalid_date(format!("{} is not a month", month)));
+            }
+        }
+        if let PartialDate::Day(year, month, day) = self {
+            if day == 0 || day > days_in_month(year, month) {
+                return Err(invalid_date(format!(
+                    "{} is not a day of {}",
+                    day,
+                    PartialDate::Month(year, month).to_sgf()
+                )));
+            }
+        }
+
+        Ok(self)
+    }
+}
+
+// Decodes a DT value into its dates, resolving the shortcuts.
+pub fn parse_dates(val: &str) -> Result<Vec<PartialDate>, SgfParseError> {
+    let mut dates: Vec<PartialDate> = vec![];
+
+    for part in val.split(',') {
+        let fields: Vec<&str> = part.split('-').collect();
+        if fields.iter().any(|field| !is_digits(field)) {
+            return Err(invalid_date(format!("'{}' is not a date", part)));
+        }
+
+        let previous = dates.last().copied();
+        let date = match (fields.as_slice(), previous) {
+            ([year], _) if year.len() == 4 => PartialDate::Year(number(year)),
+            ([year, month], _) if year.len() == 4 && month.len() == 2 => {
+                PartialDate::Month(number(year), number(month) as u8)
+            }
+            ([year, month, day], _) if year.len() == 4 && month.len() == 2 && day.len() == 2 => {
+                PartialDate::Day(number(year), number(month) as u8, number(day) as u8)
+            }
+            // MM-DD takes the year of a preceding day or month.
+            ([month, day], Some(PartialDate::Day(year, _, _) | PartialDate::Month(year, _)))
+                if month.len() == 2 && day.len() == 2 =>
+            {
+                PartialDate::Day(year, number(month) as u8, number(day) as u8)
+            }
+            // A lone two digits are a month after a month, and a day after a day.
+            ([month], Some(PartialDate::Month(year, _))) if month.len() == 2 => {
+                PartialDate::Month(year, number(month) as u8)
+            }
+            ([day], Some(PartialDate::Day(year, month, _))) if day.len() == 2 => {
+                PartialDate::Day(year, month, number(day) as u8)
+            }
+            _ => {
+                return Err(invalid_date(format!(
+                    "'{}' is not a date or a shortcut following one",
+                    part
+                )))
+            }
+        };
+
+        dates.push(date.validate()?);
+    }
+
+    Ok(dates)
+}
+
+// Whether a DT value is written as ISO dates, i.e. digits separated by "-" and ",", even if they
+// are out of range.
+pub fn is_iso_form(val: &str) -> bool {
+    val.split([',', '-']).all(is_digits)
+}
+
+// Encodes dates as a DT value, using shortcuts wherever possible.
+pub fn format_dates(dates: &[PartialDate]) -> String {
+    let mut parts = vec![];
+    let mut previous: Option<PartialDate> = None;
+
+    for date in dates {
+        let part = match (previous, date) {
+            (Some(PartialDate::Day(year, month, _)), PartialDate::Day(_, _, day))
+                if (year, month) == (date.year(), date.month().unwrap_or_default()) =>
+            {
+                format!("{:02}", day)
+            }
+            (
+                Some(PartialDate::Day(year, _, _) | PartialDate::Month(year, _)),
+                PartialDate::Day(_, month, day),
+            ) if year == date.year() => format!("{:02}-{:02}", month, day),
+            (Some(PartialDate::Month(year, _)), PartialDate::Month(_, month))
+                if year == date.year() =>
+            {
+                format!("{:02}", month)
+            }
+            _ => date.to_sgf(),
+        };
+
+        parts.push(part);
+        previous = Some(*date);
+    }
+
+    parts.join(",")
+}
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+    let leap = (year.is_multiple_of(4) && !year.is_multiple_of(100)) || year.is_multiple_of(400);
+
+    match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+fn is_digits(field: &str) -> bool {
+    !field.is_empty() && field.chars().all(|c| c.is_ascii_digit())
+}
+
+// Only called on at most four digits, so this can't overflow.
+fn number(field: &str) -> u16 {
+    field.parse().unwrap_or_default()
+}
+
+fn invalid_date(details: String) -> SgfParseError {
+    SgfParseError::new(ErrorKind::InvalidValue(details))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_dates, parse_dates, PartialDate};
+    use test_case::test_case;
+
+    #[test_case("1996", vec![PartialDate::Year(1996)] ; "Year")]
+    #[test_case("1996-12", vec![PartialDate::Month(1996, 12)] ; "Month")]
+    #[test_case("1996-05-06", vec![PartialDate::Day(1996, 5, 6)] ; "Day")]
+    #[test_case("1996-05,06", vec![PartialDate::Month(1996, 5), PartialDate::Month(1996, 6)] ; "Month shortcut")]
+    #[test_case("1996-05-06,07,08", vec![PartialDate::Day(1996, 5, 6), PartialDate::Day(1996, 5, 7), PartialDate::Day(1996, 5, 8)] ; "Day shortcuts")]
+    #[test_case("1996,1997", vec![PartialDate::Year(1996), PartialDate::Year(1997)] ; "Years")]
+    #[test_case("1996-12-27,28,1997-01-03,04", vec![PartialDate::Day(1996, 12, 27), PartialDate::Day(1996, 12, 28), PartialDate::Day(1997, 1, 3), PartialDate::Day(1997, 1, 4)] ; "Across years")]
+    #[test_case("1997-05-30,06-02", vec![PartialDate::Day(1997, 5, 30), PartialDate::Day(1997, 6, 2)] ; "Month and day shortcut")]
+    #[test_case("1997-05,06-02", vec![PartialDate::Month(1997, 5), PartialDate::Day(1997, 6, 2)] ; "Day after month")]
+    #[test_case("2000-02-29", vec![PartialDate::Day(2000, 2, 29)] ; "Leap day")]
+    fn can_parse_dates(val: &str, expected: Vec<PartialDate>) {
+        assert_eq!(parse_dates(val).unwrap(), expected);
+    }
+
+    #[test_case("" ; "Empty")]
+    #[test_case("96-05-06" ; "Two digit year")]
+    #[test_case("1996/05/06" ; "Slashes")]
+    #[test_case("1996-13" ; "Month out of range")]
+    #[test_case("1996-04-31" ; "Day out of range")]
+    #[test_case("1900-02-29" ; "Not a leap year")]
+    #[test_case("1996-5-6" ; "Single digit fields")]
+    #[test_case("1996,06" ; "Shortcut after year")]
+    #[test_case("1996-05-06,123" ; "Three digit shortcut")]
+    #[test_case("05-06" ; "Shortcut without date")]
+    #[test_case("1996-05-06, 07" ; "Space")]
+    fn malformed_date_is_error(val: &str) {
+        assert!(parse_dates(val).is_err());
+    }
+
+    #[test_case("1996-05-06,07,08" ; "Days")]
+    #[test_case("1996-05,06" ; "Months")]
+    #[test_case("1996-12-27,28,1997-01-03,04" ; "Across years")]
+    #[test_case("1997-05-30,06-02" ; "Across months")]
+    fn shortcuts_round_trip(val: &str) {
+        assert_eq!(format_dates(&parse_dates(val).unwrap()), val);
+    }
+
+    #[test]
+    fn formats_with_shortcuts() {
+        let dates = parse_dates("1997-05-05,1997-05-06,1997-06-01").unwrap();
+
+        assert_eq!(format_dates(&dates), "1997-05-05,06,06-01");
+    }
+}
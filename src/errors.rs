@@ -1,20 +1,227 @@
+use crate::lexer::TokenKind;
 use std::fmt::{Display, Formatter};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    UnexpectedCharacter(char),
+    UnexpectedToken {
+        expected: TokenKind,
+        found: TokenKind,
+    },
+    UnexpectedEndOfInput(TokenKind),
+    UnterminatedValue,
+    // A GameTree is missing its closing parenthesis, or a closing parenthesis has no opening one.
+    UnbalancedTree,
+    EmptyCollection,
+    // Every GameTree must start with at least one Node.
+    EmptyGameTree,
+    // The property identifier is not part of the SGF spec.
+    UnknownProperty,
+    // The value is not valid for the property it belongs to; see `SgfParseError::property`.
+    InvalidValue(String),
+    DuplicateProperty,
+    // A root property outside the root node.
+    MisplacedRootProperty,
+    // Game-info properties in more than one node on the same path.
+    RepeatedGameInfo,
+    // Move and setup properties in the same node.
+    MixedMoveAndSetup,
+    // The source could not be read.
+    Io(String),
+    // The charset given by CA is unknown, or the source doesn't match it.
+    Charset(String),
+    // Text outside the SGF grammar, e.g. a mail header before the first GameTree.
+    SkippedText(String),
+    // An identifier with lowercase letters, as allowed before FF[4], e.g. "AddBlack".
+    LowercaseIdentifier(String),
+    // A "]" inside a value that isn't escaped.
+    UnescapedBracket,
+    // A move that can't be played on the board during replay, e.g. onto an occupied point.
+    IllegalMove(String),
+    // The path doesn't lead to a node; holds the path up to the first missing child.
+    InvalidPath(Vec<usize>),
+    // No codec is registered for the game given by GM; its points and moves are kept as written.
+    UnsupportedGame(u32),
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorKind::UnexpectedCharacter(character) => {
+                write!(f, "Unexpected character '{}'", character)
+            }
+            ErrorKind::UnexpectedToken { expected, found } => {
+                write!(f, "Expected {:?} but found {:?}", expected, found)
+            }
+            ErrorKind::UnexpectedEndOfInput(expected) => {
+                write!(f, "Expected {:?} but reached the end of input", expected)
+            }
+            ErrorKind::UnterminatedValue => write!(f, "Unterminated property value"),
+            ErrorKind::UnbalancedTree => write!(f, "Unbalanced game tree parentheses"),
+            ErrorKind::EmptyCollection => {
+                write!(f, "A collection must contain at least one game tree")
+            }
+            ErrorKind::EmptyGameTree => write!(f, "Game tree does not start with a node"),
+            ErrorKind::UnknownProperty => write!(f, "Unknown property"),
+            ErrorKind::InvalidValue(details) => write!(f, "Invalid value: {}", details),
+            ErrorKind::DuplicateProperty => write!(f, "Property appears more than once in a node"),
+            ErrorKind::MisplacedRootProperty => {
+                write!(f, "Root property is only allowed in the root node")
+            }
+            ErrorKind::RepeatedGameInfo => {
+                write!(
+                    f,
+                    "Game-info properties already appear earlier on this path"
+                )
+            }
+            ErrorKind::MixedMoveAndSetup => {
+                write!(f, "Move and setup properties can't be mixed in one node")
+            }
+            ErrorKind::Io(details) => write!(f, "Could not read source: {}", details),
+            ErrorKind::Charset(details) => write!(f, "Charset problem: {}", details),
+            ErrorKind::SkippedText(text) => write!(f, "Skipped text '{}'", text),
+            ErrorKind::LowercaseIdentifier(ident) => {
+                write!(f, "Property identifier '{}' has lowercase letters", ident)
+            }
+            ErrorKind::UnescapedBracket => write!(f, "Unescaped ']' in property value"),
+            ErrorKind::IllegalMove(details) => write!(f, "Illegal move: {}", details),
+            ErrorKind::InvalidPath(path) => write!(f, "No node at path {:?}", path),
+            ErrorKind::UnsupportedGame(game) => write!(f, "No codec for game GM[{}]", game),
+        }
+    }
+}
+
+// Where in the source an error occurred. `line` and `column` start at 1; `column` counts chars,
+// while `offset` is the byte offset into the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    pub fn from_offset(source: &str, offset: usize) -> Self {
+        let before = &source[..offset];
+        let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+
+        Position {
+            offset,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SgfParseError {
-    details: String,
+    kind: ErrorKind,
+    position: Option<Position>,
+    property: Option<String>,
+    node: Option<usize>,
 }
 
 impl SgfParseError {
-    pub fn new(details: String) -> Self {
-        SgfParseError { details }
+    pub fn new(kind: ErrorKind) -> Self {
+        SgfParseError {
+            kind,
+            position: None,
+            property: None,
+            node: None,
+        }
+    }
+
+    pub fn with_position(mut self, position: Position) -> Self {
+        self.position = Some(position);
+        self
+    }
+
+    pub fn with_property(mut self, property: &str) -> Self {
+        self.property = Some(String::from(property));
+        self
+    }
+
+    pub fn with_node(mut self, node: usize) -> Self {
+        self.node = Some(node);
+        self
+    }
+
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    pub fn position(&self) -> Option<Position> {
+        self.position
+    }
+
+    // The identifier of the property the error occurred in, if any.
+    pub fn property(&self) -> Option<&str> {
+        self.property.as_deref()
+    }
+
+    // The index of the node the error occurred in, numbered in file order starting with zero.
+    pub fn node(&self) -> Option<usize> {
+        self.node
     }
 }
 
 impl Display for SgfParseError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.details)
+        write!(f, "{}", self.kind)?;
+
+        if let Some(property) = &self.property {
+            write!(f, " in property {}", property)?;
+        }
+        if let Some(node) = self.node {
+            write!(f, " in node {}", node)?;
+        }
+        if let Some(position) = self.position {
+            write!(f, " at line {}, column {}", position.line, position.column)?;
+        }
+
+        Ok(())
     }
 }
 
 impl std::error::Error for SgfParseError {}
+
+impl From<std::io::Error> for SgfParseError {
+    fn from(err: std::io::Error) -> Self {
+        SgfParseError::new(ErrorKind::Io(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ErrorKind, Position, SgfParseError};
+
+    #[test]
+    fn position_counts_lines_and_chars() {
+        let source = "(;C[first\nsecond 棋]\n;B[";
+        let offset = source.find(']').unwrap();
+
+        let position = Position::from_offset(source, offset);
+
+        assert_eq!(position.offset, offset);
+        assert_eq!(position.line, 2);
+        assert_eq!(position.column, 9);
+    }
+
+    #[test]
+    fn display_includes_location() {
+        let error =
+            SgfParseError::new(ErrorKind::InvalidValue(String::from("'x' is not a number")))
+                .with_property("FF")
+                .with_node(3)
+                .with_position(Position {
+                    offset: 10,
+                    line: 2,
+                    column: 4,
+                });
+
+        assert_eq!(
+            error.to_string(),
+            "Invalid value: 'x' is not a number in property FF in node 3 at line 2, column 4"
+        );
+    }
+}
@@ -0,0 +1,241 @@
+// From the SGF spec (https://www.red-bean.com/sgf/properties.html#RE):
+// RE provides the result of the game. It is MANDATORY to use the following format:
+// "0" (zero) or "Draw" for a draw (jigo), "B+" ["score"] for a black win and "W+" ["score"] for a
+// white win. Score is optional (some games don't have a score e.g. chess). If the score is given
+// it has to be given as a real value, e.g. "B+0.5", "W+64", "B+12.5".
+// "B+R"/"B+Resign" and "W+R"/"W+Resign" for a win by resignation, "B+T"/"B+Time" and
+// "W+T"/"W+Time" for a win on time, "B+F"/"B+Forfeit" and "W+F"/"W+Forfeit" for a win by forfeit,
+// "Void" for no result or suspended play and "?" for an unknown result.
+
+// Records in the wild often use other spellings, e.g. "Jigo", "b+res" or "White wins by 3.5";
+// `parse_lenient` accepts the common ones.
+
+use crate::errors::{ErrorKind, SgfParseError};
+use crate::property::Color;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameResult {
+    Win(Color, WinReason),
+    Draw,
+    Void,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WinReason {
+    // The margin in points.
+    Score(f64),
+    Resignation,
+    Time,
+    Forfeit,
+    // A win without a score, i.e. "B+".
+    Unspecified,
+}
+
+impl GameResult {
+    // Parses the formats given by the spec.
+    pub fn parse(val: &str) -> Result<Self, SgfParseError> {
+        let result = match val {
+            "0" | "Draw" => GameResult::Draw,
+            "Void" => GameResult::Void,
+            "?" => GameResult::Unknown,
+            _ => {
+                let (winner, reason) = split_winner(val).ok_or_else(|| invalid_result(val))?;
+                let reason = match reason {
+                    "" => WinReason::Unspecified,
+                    "R" | "Resign" => WinReason::Resignation,
+                    "T" | "Time" => WinReason::Time,
+                    "F" | "Forfeit" => WinReason::Forfeit,
+                    _ => WinReason::Score(parse_score(reason).ok_or_else(|| invalid_result(val))?),
+                };
+
+                GameResult::Win(winner, reason)
+            }
+        };
+
+        Ok(result)
+    }
+
+    // Like `parse`, but ignores case and spaces and accepts common non-standard spellings.
+    pub fn parse_lenient(val: &str) -> Result<Self, SgfParseError> {
+        let mut normalized: String = val
+            .chars()
+            .filter(|character| !character.is_whitespace())
+            .collect::<String>()
+            .to_ascii_uppercase()
+            .replace("BLACK", "B")
+            .replace("WHITE", "W")
+            .replace("WINSBY", "+")
+            .replace("WINS", "+")
+            .replace("WIN", "+")
+            .replace(',', ".");
+        while normalized.contains("++") {
+            normalized = normalized.replace("++", "+");
+        }
+
+        let result = match normalized.as_str() {
+            "0" | "0.0" | "DRAW" | "JIGO" | "B+0" | "W+0" => GameResult::Draw,
+            "VOID" | "NORESULT" => GameResult::Void,
+            "?" | "" | "UNKNOWN" => GameResult::Unknown,
+            _ => {
+                let (winner, reason) =
+                    split_winner(&normalized).ok_or_else(|| invalid_result(val))?;
+                let reason = match reason.trim_start_matches("BY") {
+                    "" => WinReason::Unspecified,
+                    "R" | "RES" | "RESIGN" | "RESIGNED" | "RESIGNATION" => WinReason::Resignation,
+                    "T" | "TIME" | "TIMEOUT" | "ONTIME" => WinReason::Time,
+                    "F" | "FORFEIT" => WinReason::Forfeit,
+                    score => {
+                        let score = ["POINTS", "POINT", "PTS", "PT"]
+                            .iter()
+                            .find_map(|unit| score.strip_suffix(unit))
+                            .unwrap_or(score);
+
+                        WinReason::Score(parse_score(score).ok_or_else(|| invalid_result(val))?)
+                    }
+                };
+
+                GameResult::Win(winner, reason)
+            }
+        };
+
+        Ok(result)
+    }
+
+    // The canonical text of the result, e.g. "B+R" rather than "B+Resign".
+    pub fn to_sgf(&self) -> String {
+        match self {
+            GameResult::Win(winner, reason) => {
+                let winner = match winner {
+                    Color::Black => 'B',
+                    Color::White => 'W',
+                };
+                let reason = match reason {
+                    WinReason::Score(score) => score.to_string(),
+                    WinReason::Resignation => String::from("R"),
+                    WinReason::Time => String::from("T"),
+                    WinReason::Forfeit => String::from("F"),
+                    WinReason::Unspecified => String::new(),
+                };
+
+                format!("{}+{}", winner, reason)
+            }
+            GameResult::Draw => String::from("0"),
+            GameResult::Void => String::from("Void"),
+            GameResult::Unknown => String::from("?"),
+        }
+    }
+
+    pub fn winner(&self) -> Option<Color> {
+        match self {
+            GameResult::Win(winner, _) => Some(*winner),
+            _ => None,
+        }
+    }
+
+    // The score margin, positive if Black wins; zero for a draw and `None` without a score.
+    pub fn margin(&self) -> Option<f64> {
+        match self {
+            GameResult::Win(Color::Black, WinReason::Score(score)) => Some(*score),
+            GameResult::Win(Color::White, WinReason::Score(score)) => Some(-score),
+            GameResult::Draw => Some(0.0),
+            _ => None,
+        }
+    }
+}
+
+fn split_winner(val: &str) -> Option<(Color, &str)> {
+    if let Some(reason) = val.strip_prefix("B+") {
+        Some((Color::Black, reason))
+    } else {
+        val.strip_prefix("W+").map(|reason| (Color::White, reason))
+    }
+}
+
+// A score is a non-negative real number, e.g. "0.5", "64" or "12.5".
+fn parse_score(val: &str) -> Option<f64> {
+    let (whole, fraction) = val.split_once('.').unwrap_or((val, "0"));
+
+    let is_digits = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit());
+    if !is_digits(whole) || !is_digits(fraction) {
+        return None;
+    }
+
+    val.parse::<f64>().ok()
+}
+
+fn invalid_result(val: &str) -> SgfParseError {
+    SgfParseError::new(ErrorKind::InvalidValue(format!(
+        "'{}' is not a game result",
+        val
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GameResult, WinReason};
+    use crate::property::Color;
+    use test_case::test_case;
+
+    #[test_case("B+R", GameResult::Win(Color::Black, WinReason::Resignation) ; "Resignation")]
+    #[test_case("W+Resign", GameResult::Win(Color::White, WinReason::Resignation) ; "Resign")]
+    #[test_case("W+3.5", GameResult::Win(Color::White, WinReason::Score(3.5)) ; "Score")]
+    #[test_case("B+64", GameResult::Win(Color::Black, WinReason::Score(64.0)) ; "Whole score")]
+    #[test_case("B+T", GameResult::Win(Color::Black, WinReason::Time) ; "Time")]
+    #[test_case("W+Forfeit", GameResult::Win(Color::White, WinReason::Forfeit) ; "Forfeit")]
+    #[test_case("B+", GameResult::Win(Color::Black, WinReason::Unspecified) ; "No score")]
+    #[test_case("0", GameResult::Draw ; "Zero")]
+    #[test_case("Draw", GameResult::Draw ; "Draw")]
+    #[test_case("Void", GameResult::Void ; "Void")]
+    #[test_case("?", GameResult::Unknown ; "Unknown")]
+    fn can_parse_result(val: &str, expected: GameResult) {
+        assert_eq!(GameResult::parse(val).unwrap(), expected);
+        assert_eq!(GameResult::parse_lenient(val).unwrap(), expected);
+    }
+
+    #[test_case("b+r" ; "Lower case")]
+    #[test_case("Jigo" ; "Jigo")]
+    #[test_case("W+3,5" ; "Decimal comma")]
+    #[test_case("B + Resign" ; "Spaces")]
+    #[test_case("B+-1" ; "Negative score")]
+    #[test_case("Black" ; "No plus")]
+    fn strict_parse_rejects_non_standard(val: &str) {
+        assert!(GameResult::parse(val).is_err());
+    }
+
+    #[test_case("b+r", GameResult::Win(Color::Black, WinReason::Resignation) ; "Lower case")]
+    #[test_case("B+Res", GameResult::Win(Color::Black, WinReason::Resignation) ; "Abbreviated")]
+    #[test_case("White wins by resignation", GameResult::Win(Color::White, WinReason::Resignation) ; "Sentence")]
+    #[test_case("Black wins by 12.5 points", GameResult::Win(Color::Black, WinReason::Score(12.5)) ; "Sentence with score")]
+    #[test_case("W+3,5", GameResult::Win(Color::White, WinReason::Score(3.5)) ; "Decimal comma")]
+    #[test_case("B + Time", GameResult::Win(Color::Black, WinReason::Time) ; "Spaces")]
+    #[test_case("W+0.5pts", GameResult::Win(Color::White, WinReason::Score(0.5)) ; "Points suffix")]
+    #[test_case("Black wins", GameResult::Win(Color::Black, WinReason::Unspecified) ; "Sentence without score")]
+    #[test_case("Jigo", GameResult::Draw ; "Jigo")]
+    #[test_case("no result", GameResult::Void ; "No result")]
+    fn can_parse_lenient_result(val: &str, expected: GameResult) {
+        assert_eq!(GameResult::parse_lenient(val).unwrap(), expected);
+    }
+
+    #[test_case("B+Resign", "B+R" ; "Resignation")]
+    #[test_case("W+Time", "W+T" ; "Time")]
+    #[test_case("B+Forfeit", "B+F" ; "Forfeit")]
+    #[test_case("W+3.5", "W+3.5" ; "Score")]
+    #[test_case("B+12.0", "B+12" ; "Whole score")]
+    #[test_case("Draw", "0" ; "Draw")]
+    #[test_case("Void", "Void" ; "Void")]
+    #[test_case("?", "?" ; "Unknown")]
+    fn writes_canonical_result(val: &str, expected: &str) {
+        let result = GameResult::parse(val).unwrap();
+
+        assert_eq!(result.to_sgf(), expected);
+        assert_eq!(GameResult::parse(expected).unwrap(), result);
+    }
+
+    #[test_case("Black resigned" ; "No plus")]
+    #[test_case("W+lots" ; "Not a score")]
+    #[test_case("Unfinished game" ; "Free text")]
+    fn lenient_parse_rejects_unknown(val: &str) {
+        assert!(GameResult::parse_lenient(val).is_err());
+    }
+}
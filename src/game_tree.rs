@@ -1,7 +1,15 @@
-use crate::chars;
-use crate::errors::SgfParseError;
+use crate::codec::{self, Codecs, GameCodec, Go};
+use crate::errors::{ErrorKind, SgfParseError};
+use crate::lexer::TokenKind;
+use crate::navigation::{self, BreadthFirst, DepthFirst, Location, MainLine};
 use crate::node::Node;
+use crate::parser::Parser;
+use crate::point::BoardSize;
+use crate::property::{Property, PropertyValue};
+use crate::writer::{WriteOptions, Writer};
+use std::sync::Arc;
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct GameTree {
     // Called `leaves` instead of `nodes` since `Node` has a specific meaning in SFG files.
     leaves: Vec<GameTree>,
@@ -9,135 +17,415 @@ pub struct GameTree {
 }
 
 impl GameTree {
-    pub fn parse(source: &str) -> Result<(Self, usize), SgfParseError> {
+    pub fn sequence(&self) -> &[Node] {
+        &self.sequence
+    }
+
+    pub fn leaves(&self) -> &[GameTree] {
+        &self.leaves
+    }
+
+    pub fn variation(&self, index: usize) -> Option<&GameTree> {
+        self.leaves.get(index)
+    }
+
+    pub fn root(&self) -> &Node {
+        &self.sequence[0]
+    }
+
+    // Every node of the tree, in depth-first order starting with the root.
+    pub(crate) fn nodes_mut(&mut self) -> Vec<&mut Node> {
+        let mut nodes = vec![];
+        let mut pending = vec![self];
+
+        while let Some(GameTree { leaves, sequence }) = pending.pop() {
+            nodes.extend(sequence.iter_mut());
+            pending.extend(leaves.iter_mut().rev());
+        }
+
+        nodes
+    }
+
+    // The FF version given in the root node; without one a file is FF[1].
+    pub fn version(&self) -> u32 {
+        match self.root().get("FF").and_then(|prop| prop.values.first()) {
+            Some(PropertyValue::Number(version, _, _)) => *version,
+            _ => 1,
+        }
+    }
+
+    // The game given by GM in the root node, Go if there is none.
+    pub fn game(&self) -> u32 {
+        match self.root().get("GM").and_then(|prop| prop.values.first()) {
+            Some(PropertyValue::Number(game, _, _)) => *game,
+            _ => codec::GO,
+        }
+    }
+
+    // Points and moves are written by the codec of the game, e.g. with `Property::to_sgf_with`.
+    // This is that codec and the board size, or Go's for games without a codec.
+    pub fn codec(&self, codecs: &Codecs) -> (Arc<dyn GameCodec>, BoardSize) {
+        let codec: Arc<dyn GameCodec> = codecs.get(self.game()).unwrap_or_else(|| Arc::new(Go));
+        let size = self
+            .root()
+            .get("SZ")
+            .and_then(|prop| prop.values.first())
+            .and_then(BoardSize::from_value)
+            .unwrap_or_else(|| codec.default_size());
+
+        (codec, size)
+    }
+
+    pub fn main_line(&self) -> MainLine<'_> {
+        MainLine::new(self)
+    }
+
+    // Nodes in the order they are stored in the file, matching the numbering in `node.rs`.
+    pub fn depth_first(&self) -> DepthFirst<'_> {
+        DepthFirst::new(self)
+    }
+
+    pub fn breadth_first(&self) -> BreadthFirst<'_> {
+        BreadthFirst::new(self)
+    }
+
+    // The nodes that directly follow `node`; more than one if a fork starts after it.
+    pub fn children(&self, node: &Node) -> Vec<&Node> {
+        match navigation::find(self, node) {
+            Some(trail) => trail
+                .last()
+                .unwrap()
+                .children()
+                .iter()
+                .map(Location::node)
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    // The node before `node`; `None` for the root or a node that isn't part of this tree.
+    pub fn parent(&self, node: &Node) -> Option<&Node> {
+        let trail = navigation::find(self, node)?;
+
+        trail.len().checked_sub(2).map(|index| trail[index].node())
+    }
+
+    // The node reached by following the child indices in `path` from the root.
+    pub fn node_at(&self, path: &[usize]) -> Option<&Node> {
+        let mut location = Location::root(self);
+
+        for index in path {
+            location = location.child(*index)?;
+        }
+
+        Some(location.node())
+    }
+
+    // The first `id` property on the way from the root to the node at `path`. Game-info
+    // properties are usually in the root, but may be in the node where a game first becomes
+    // distinguishable from the others in the tree.
+    pub fn game_info(&self, path: &[usize], id: &str) -> Option<&Property> {
+        (0..=path.len())
+            .filter_map(|depth| self.node_at(&path[..depth]))
+            .find_map(|node| node.get(id))
+    }
+
+    // The child indices leading from the root to `node`, see `node_at`.
+    pub fn path_of(&self, node: &Node) -> Option<Vec<usize>> {
+        let trail = navigation::find(self, node)?;
+
+        Some(
+            trail
+                .windows(2)
+                .map(|pair| {
+                    pair[0]
+                        .children()
+                        .iter()
+                        .position(|child| child.is(pair[1].node()))
+                        .unwrap()
+                })
+                .collect(),
+        )
+    }
+
+    pub fn to_sgf(&self, options: &WriteOptions) -> String {
+        let mut writer = Writer::new(options);
+        writer.write_game_tree(self);
+
+        writer.finish()
+    }
+
+    pub fn parse(parser: &mut Parser) -> Result<Self, SgfParseError> {
         let mut leaves: Vec<GameTree> = vec![];
         let mut sequence: Vec<Node> = vec![];
 
-        let mut skip_counter = 0;
+        let start = parser.lexer.expect(TokenKind::TreeStart)?.span.start;
 
-        let _counting_node = false;
+        // A GameTree is a Sequence of at least one Node, followed by any nested GameTrees.
+        while parser.lexer.peek_kind() == Some(TokenKind::NodeStart) {
+            sequence.push(Node::parse(parser)?);
+        }
 
-        for (index, character) in source.chars().enumerate() {
-            let index = index + 1;
-            if skip_counter > 0 {
-                skip_counter -= 1;
-                continue;
-            }
+        if sequence.is_empty() {
+            return Err(match parser.lexer.next() {
+                Some(Err(err)) => err,
+                Some(Ok(token)) => parser
+                    .lexer
+                    .error_at(ErrorKind::EmptyGameTree, token.span.start),
+                None => parser.lexer.error_at(ErrorKind::UnbalancedTree, start),
+            });
+        }
 
-            match character {
-                chars::TREE_START => {
-                    // We encountered a nested GameTree.
-                    let remaining_content = source.split_at(index);
-                    match GameTree::parse(remaining_content.1) {
-                        Ok(leaf_result) => {
-                            leaves.push(leaf_result.0);
-                            skip_counter = leaf_result.1;
-                        }
-                        Err(_) => break,
-                    }
-                }
-                chars::TREE_END => {
-                    return Ok((GameTree { leaves, sequence }, index));
-                }
-                chars::NODE_START => {
-                    // We encountered a Node.
-                    let remaining_content = source.split_at(index);
-                    let node_result = Node::parse(remaining_content.1)?;
-
-                    sequence.push(node_result.0);
-                    skip_counter = node_result.1;
-                }
-                // White space (space, tab, carriage return, line feed, vertical tab and so on) may appear
-                // anywhere between PropValues, Properties, Nodes, Sequences and GameTrees.
-                ' ' | '\n' | '\t' => (),
-                _ => {
-                    todo!()
-                }
-            }
+        // Each variation is a separate path, so game-info seen in one doesn't count for the next.
+        let game_info_seen = parser.game_info_seen;
+        while parser.lexer.peek_kind() == Some(TokenKind::TreeStart) {
+            // We encountered a nested GameTree.
+            leaves.push(GameTree::parse(parser)?);
+            parser.game_info_seen = game_info_seen;
+        }
+
+        if parser.lexer.peek().is_none() {
+            // The input ended before this GameTree was closed.
+            let repair = parser.lexer.error_at(ErrorKind::UnbalancedTree, start);
+            parser.recover(repair)?;
+
+            return Ok(GameTree { leaves, sequence });
         }
+        parser.lexer.expect(TokenKind::TreeEnd)?;
 
-        return Ok((GameTree { leaves, sequence }, source.len()));
+        Ok(GameTree { leaves, sequence })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::GameTree;
+    use crate::errors::ErrorKind;
+    use crate::lexer::TokenKind;
+    use crate::node::Node;
+    use crate::parser::Parser;
+    use crate::property::PropertyValue;
+
+    fn parse(content: &str) -> GameTree {
+        GameTree::parse(&mut Parser::new(content)).unwrap()
+    }
 
     #[test]
     fn can_parse_single_game_tree() {
-        let content = ";FF[4]";
-        let tree = GameTree::parse(content).unwrap().0;
+        let content = "(;FF[4])";
+        let tree = parse(content);
 
-        let node = tree.sequence.get(0).unwrap();
+        let node = tree.sequence.first().unwrap();
         assert_eq!(node.properties.len(), 1);
 
-        let prop = node.properties.get(0).unwrap();
+        let prop = node.properties.first().unwrap();
         assert_eq!(prop.id, "FF");
     }
 
     #[test]
     fn can_parse_nested_game_tree() {
-        let content = ";FF[4] (;AP[windows:95])";
-        let tree = GameTree::parse(content).unwrap().0;
+        let content = "(;FF[4] (;C[windows:95]))";
+        let tree = parse(content);
 
         assert_eq!(tree.leaves.len(), 1);
 
-        let nested = tree.leaves.get(0).unwrap();
+        let nested = tree.leaves.first().unwrap();
+
+        assert_eq!(nested.leaves.len(), 0);
+    }
+
+    #[test]
+    fn can_parse_consecutive_nested_game_tree() {
+        let content = "(;FF[4] (;V[3]) (;V[2]))";
+        let tree = parse(content);
+
+        assert_eq!(tree.sequence.len(), 1);
+        assert_eq!(tree.leaves.len(), 2);
+
+        let first_nested = tree.leaves.first().unwrap();
+        assert_eq!(first_nested.sequence.len(), 1);
+        assert_eq!(first_nested.leaves.len(), 0);
+
+        let second_nested = tree.leaves.get(1).unwrap();
+        assert_eq!(second_nested.sequence.len(), 1);
+        assert_eq!(second_nested.leaves.len(), 0);
+    }
+
+    #[test]
+    fn can_parse_complex_nested_game_tree() {
+        let content = "(;FF[4];V[4] (;V[3] (;V[2])) (;V[3](;V[2](;V[1]))))";
+        let tree = parse(content);
+
+        assert_eq!(tree.sequence.len(), 2);
+        assert_eq!(tree.leaves.len(), 2);
+
+        let nested = tree.leaves.first().unwrap();
+        assert_eq!(nested.leaves.len(), 1);
 
+        let nested = nested.leaves.first().unwrap();
         assert_eq!(nested.leaves.len(), 0);
+
+        let nested = tree.leaves.get(1).unwrap();
+        assert_eq!(nested.leaves.len(), 1);
+
+        let nested = nested.leaves.first().unwrap();
+        assert_eq!(nested.leaves.len(), 1);
+
+        let nested = nested.leaves.first().unwrap();
+        assert_eq!(nested.leaves.len(), 0);
+    }
+
+    fn names(nodes: Vec<&Node>) -> Vec<String> {
+        nodes
+            .iter()
+            .map(|node| match &node.properties[0].values[0] {
+                PropertyValue::SimpleText(name) => name.clone(),
+                other => panic!("Expected a name, found {:?}", other),
+            })
+            .collect()
+    }
+
+    // The example from the node numbering comment in `node.rs`.
+    const NUMBERED: &str =
+        "(;N[root](;N[a];N[b](;N[c])(;N[d];N[e]))(;N[f](;N[g];N[h];N[i])(;N[j])))";
+
+    #[test]
+    fn depth_first_is_file_order() {
+        let tree = parse(NUMBERED);
+
+        assert_eq!(
+            names(tree.depth_first().collect()),
+            vec!["root", "a", "b", "c", "d", "e", "f", "g", "h", "i", "j"]
+        );
+    }
+
+    #[test]
+    fn breadth_first_is_level_order() {
+        let tree = parse(NUMBERED);
+
+        assert_eq!(
+            names(tree.breadth_first().collect()),
+            vec!["root", "a", "f", "b", "g", "j", "c", "d", "h", "e", "i"]
+        );
+    }
+
+    #[test]
+    fn main_line_takes_first_variations() {
+        let tree = parse(NUMBERED);
+
+        assert_eq!(
+            names(tree.main_line().collect()),
+            vec!["root", "a", "b", "c"]
+        );
+    }
+
+    #[test]
+    fn can_find_children_and_parent() {
+        let tree = parse(NUMBERED);
+        let b = tree.node_at(&[0, 0]).unwrap();
+
+        assert_eq!(names(tree.children(b)), vec!["c", "d"]);
+        assert_eq!(names(tree.children(tree.root())), vec!["a", "f"]);
+        assert!(tree.children(tree.node_at(&[1, 1]).unwrap()).is_empty());
+
+        assert_eq!(names(vec![tree.parent(b).unwrap()]), vec!["a"]);
+
+        let g = tree.node_at(&[1, 0]).unwrap();
+        assert_eq!(names(vec![tree.parent(g).unwrap()]), vec!["f"]);
+        assert!(tree.parent(tree.root()).is_none());
+    }
+
+    #[test]
+    fn can_follow_paths() {
+        let tree = parse(NUMBERED);
+
+        let e = tree.node_at(&[0, 0, 1, 0]).unwrap();
+        assert_eq!(names(vec![e]), vec!["e"]);
+        assert_eq!(tree.path_of(e), Some(vec![0, 0, 1, 0]));
+        assert_eq!(tree.path_of(tree.root()), Some(vec![]));
+
+        assert!(tree.node_at(&[2]).is_none());
+        assert!(tree.node_at(&[0, 1]).is_none());
+    }
+
+    #[test]
+    fn nodes_of_other_trees_are_not_found() {
+        let tree = parse(NUMBERED);
+        let other = parse(NUMBERED);
+
+        assert!(tree.parent(other.node_at(&[0]).unwrap()).is_none());
+        assert!(tree.path_of(other.root()).is_none());
+    }
+
+    #[test]
+    fn can_access_variations() {
+        let collection = crate::parse_file("resources/example.sgf").unwrap();
+        let tree = &collection.game_trees()[0];
+
+        assert_eq!(tree.leaves().len(), 5);
+        assert_eq!(tree.variation(1).unwrap().sequence().len(), 4);
+        assert!(tree.variation(5).is_none());
+        assert_eq!(tree.depth_first().count(), tree.breadth_first().count());
+    }
+
+    #[test]
+    fn unclosed_game_tree_is_error() {
+        let error = GameTree::parse(&mut Parser::new("(;FF[4] (;V[3]"))
+            .err()
+            .unwrap();
+
+        assert_eq!(*error.kind(), ErrorKind::UnbalancedTree);
+        assert_eq!(error.position().unwrap().offset, 8);
+    }
+
+    #[test]
+    fn repeated_game_info_is_error() {
+        let error = GameTree::parse(&mut Parser::new("(;PB[Black];B[aa];PW[White])"))
+            .err()
+            .unwrap();
+
+        assert_eq!(*error.kind(), ErrorKind::RepeatedGameInfo);
+        assert_eq!(error.property(), Some("PW"));
+        assert_eq!(error.node(), Some(2));
+    }
+
+    #[test]
+    fn game_info_may_appear_once_per_variation() {
+        let tree = parse("(;FF[4];B[aa](;PB[First];W[bb])(;PB[Second];W[cc]))");
+
+        assert_eq!(tree.leaves.len(), 2);
+    }
+
+    #[test]
+    fn game_info_after_variation_start_is_error() {
+        let error = GameTree::parse(&mut Parser::new(
+            "(;FF[4](;PB[First])(;PB[Second];RE[B+R]))",
+        ))
+        .err()
+        .unwrap();
+
+        assert_eq!(*error.kind(), ErrorKind::RepeatedGameInfo);
+    }
+
+    #[test]
+    fn empty_game_tree_is_error() {
+        let error = GameTree::parse(&mut Parser::new("()")).err().unwrap();
+
+        assert_eq!(*error.kind(), ErrorKind::EmptyGameTree);
+    }
+
+    #[test]
+    fn node_after_nested_game_tree_is_error() {
+        let error = GameTree::parse(&mut Parser::new("(;FF[4] (;V[3]) ;V[2])"))
+            .err()
+            .unwrap();
+
+        assert_eq!(
+            *error.kind(),
+            ErrorKind::UnexpectedToken {
+                expected: TokenKind::TreeEnd,
+                found: TokenKind::NodeStart
+            }
+        );
     }
-    //
-    // #[test]
-    // fn can_parse_consecutive_nested_game_tree() {
-    //     let content = "ab (def) (ghi)";
-    //     let tree = GameTree::parse(content).unwrap().0;
-    //
-    //     assert_eq!(tree.content, "ab");
-    //     assert_eq!(tree.leaves.len(), 2);
-    //
-    //     let first_nested = tree.leaves.get(0).unwrap();
-    //
-    //     assert_eq!(first_nested.content, "def");
-    //     assert_eq!(first_nested.leaves.len(), 0);
-    //
-    //     let second_nested = tree.leaves.get(1).unwrap();
-    //
-    //     assert_eq!(second_nested.content, "ghi");
-    //     assert_eq!(second_nested.leaves.len(), 0);
-    // }
-    //
-    // #[test]
-    // fn can_parse_complex_nested_game_tree() {
-    //     let content = "ab (def (ghi)) (jkl(mno(pqr)))";
-    //     let tree = GameTree::parse(content).unwrap().0;
-    //
-    //     assert_eq!(tree.content, "ab");
-    //     assert_eq!(tree.leaves.len(), 2);
-    //
-    //     let nested = tree.leaves.get(0).unwrap();
-    //
-    //     assert_eq!(nested.content, "def");
-    //     assert_eq!(nested.leaves.len(), 1);
-    //
-    //     let nested = nested.leaves.get(0).unwrap();
-    //
-    //     assert_eq!(nested.content, "ghi");
-    //     assert_eq!(nested.leaves.len(), 0);
-    //
-    //     let nested = tree.leaves.get(1).unwrap();
-    //
-    //     assert_eq!(nested.content, "jkl");
-    //     assert_eq!(nested.leaves.len(), 1);
-    //
-    //     let nested = nested.leaves.get(0).unwrap();
-    //
-    //     assert_eq!(nested.content, "mno");
-    //     assert_eq!(nested.leaves.len(), 1);
-    //
-    //     let nested = nested.leaves.get(0).unwrap();
-    //
-    //     assert_eq!(nested.content, "pqr");
-    //     assert_eq!(nested.leaves.len(), 0);
-    // }
 }
@@ -0,0 +1,475 @@
+// The lexer splits SGF source into tokens, so the parsers never have to re-scan string slices.
+// All positions are byte offsets into the source, which keeps slicing safe for multi-byte UTF-8
+// text (e.g. Japanese or Korean player names and comments).
+
+// From the SGF spec (https://www.red-bean.com/sgf/sgf4.html):
+// Collection = GameTree { GameTree }
+// GameTree   = "(" Sequence { GameTree } ")"
+// Sequence   = Node { Node }
+// Node       = ";" { Property }
+// Property   = PropIdent PropValue { PropValue }
+// PropIdent  = UcLetter { UcLetter }
+// PropValue  = "[" CValueType "]"
+
+use crate::chars;
+use crate::errors::{ErrorKind, Position, SgfParseError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    TreeStart,
+    TreeEnd,
+    NodeStart,
+    PropIdent,
+    PropValue,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    // For `PropValue` this is the raw text between the brackets, with escapes left untouched.
+    // For every other kind it is the text covered by `span`.
+    pub text: &'a str,
+    pub span: Span,
+}
+
+pub struct Lexer<'a> {
+    source: &'a str,
+    offset: usize,
+    peeked: Option<Option<Result<Token<'a>, SgfParseError>>>,
+    // Where the most recently lexed token starts, so a peeked token can be skipped over.
+    token_start: usize,
+    node_count: usize,
+    // Whether malformed source is repaired rather than reported as an error, see `lenient`.
+    lenient: bool,
+    repairs: Vec<SgfParseError>,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(source: &'a str) -> Self {
+        Lexer {
+            source,
+            offset: 0,
+            peeked: None,
+            token_start: 0,
+            node_count: 0,
+            lenient: false,
+            repairs: vec![],
+        }
+    }
+
+    // A lexer that skips stray characters and accepts unescaped "]" in values, recording each
+    // repair instead of failing.
+    pub fn lenient(source: &'a str) -> Self {
+        Lexer {
+            lenient: true,
+            ..Lexer::new(source)
+        }
+    }
+
+    pub fn take_repairs(&mut self) -> Vec<SgfParseError> {
+        std::mem::take(&mut self.repairs)
+    }
+
+    // Skips the source up to the next `target` char, or to the end if there is none, including
+    // a token that was peeked. Returns the offset of the skipped text and the text itself.
+    pub fn skip_until(&mut self, target: char) -> (usize, &'a str) {
+        if self.peeked.take().is_some() {
+            self.offset = self.token_start;
+        }
+
+        let start = self.offset;
+        self.offset += self.source[start..]
+            .find(target)
+            .unwrap_or(self.source.len() - start);
+
+        (start, &self.source[start..self.offset])
+    }
+
+    pub fn position_at(&self, offset: usize) -> Position {
+        Position::from_offset(self.source, offset)
+    }
+
+    // The index of the most recently consumed Node, in the file order described in `node.rs`.
+    pub fn node_index(&self) -> Option<usize> {
+        self.node_count.checked_sub(1)
+    }
+
+    pub fn peek(&mut self) -> Option<&Result<Token<'a>, SgfParseError>> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.lex_token());
+        }
+
+        self.peeked.as_ref().unwrap().as_ref()
+    }
+
+    pub fn peek_kind(&mut self) -> Option<TokenKind> {
+        match self.peek() {
+            Some(Ok(token)) => Some(token.kind),
+            _ => None,
+        }
+    }
+
+    // Consumes the next token, failing if it is missing or not of the `expected` kind.
+    pub fn expect(&mut self, expected: TokenKind) -> Result<Token<'a>, SgfParseError> {
+        match self.next() {
+            Some(Ok(token)) if token.kind == expected => Ok(token),
+            Some(Ok(token)) => Err(self.error_at(
+                ErrorKind::UnexpectedToken {
+                    expected,
+                    found: token.kind,
+                },
+                token.span.start,
+            )),
+            Some(Err(err)) => Err(err),
+            None => {
+                Err(self.error_at(ErrorKind::UnexpectedEndOfInput(expected), self.source.len()))
+            }
+        }
+    }
+
+    pub fn error_at(&self, kind: ErrorKind, offset: usize) -> SgfParseError {
+        SgfParseError::new(kind).with_position(self.position_at(offset))
+    }
+
+    fn lex_token(&mut self) -> Option<Result<Token<'a>, SgfParseError>> {
+        // White space (space, tab, carriage return, line feed, vertical tab and so on) may appear
+        // anywhere between PropValues, Properties, Nodes, Sequences and GameTrees.
+        let rest = &self.source[self.offset..];
+        let trimmed = rest.trim_start();
+        self.offset += rest.len() - trimmed.len();
+
+        let start = self.offset;
+        self.token_start = start;
+        let character = trimmed.chars().next()?;
+
+        let token = match character {
+            chars::TREE_START => self.single(TokenKind::TreeStart),
+            chars::TREE_END => self.single(TokenKind::TreeEnd),
+            chars::NODE_START => self.single(TokenKind::NodeStart),
+            chars::PROP_VAL_START => return Some(self.lex_value()),
+            // Identifiers may contain lowercase letters before FF[4], see `RawProperty::check_spelling`.
+            'A'..='Z' => {
+                let length = trimmed
+                    .find(|c: char| !c.is_ascii_alphabetic())
+                    .unwrap_or(trimmed.len());
+                self.offset += length;
+
+                Token {
+                    kind: TokenKind::PropIdent,
+                    text: &self.source[start..self.offset],
+                    span: Span {
+                        start,
+                        end: self.offset,
+                    },
+                }
+            }
+            _ if self.lenient => {
+                let length = trimmed.find(starts_token).unwrap_or(trimmed.len());
+                self.offset += length;
+                let skipped = String::from(trimmed[..length].trim_end());
+                self.repair(ErrorKind::SkippedText(skipped), start);
+
+                return self.lex_token();
+            }
+            other => {
+                // Skip the offending character, so the lexer can't get stuck on it.
+                self.offset += other.len_utf8();
+                return Some(Err(
+                    self.error_at(ErrorKind::UnexpectedCharacter(other), start)
+                ));
+            }
+        };
+
+        Some(Ok(token))
+    }
+
+    fn single(&mut self, kind: TokenKind) -> Token<'a> {
+        let start = self.offset;
+        self.offset += 1;
+
+        Token {
+            kind,
+            text: &self.source[start..self.offset],
+            span: Span {
+                start,
+                end: self.offset,
+            },
+        }
+    }
+
+    fn lex_value(&mut self) -> Result<Token<'a>, SgfParseError> {
+        let start = self.offset;
+        let content_start = start + chars::PROP_VAL_START.len_utf8();
+
+        let mut escaped = false;
+        for (index, character) in self.source[content_start..].char_indices() {
+            if escaped {
+                escaped = false;
+                continue;
+            }
+
+            match character {
+                // "\" is the escape character; an escaped "]" does not end the value.
+                chars::ESCAPE => escaped = true,
+                chars::PROP_VAL_END => {
+                    let content_end = content_start + index;
+                    if self.lenient && self.is_unescaped(content_end) {
+                        self.repair(ErrorKind::UnescapedBracket, content_end);
+                        continue;
+                    }
+
+                    self.offset = content_end + chars::PROP_VAL_END.len_utf8();
+
+                    return Ok(Token {
+                        kind: TokenKind::PropValue,
+                        text: &self.source[content_start..content_end],
+                        span: Span {
+                            start,
+                            end: self.offset,
+                        },
+                    });
+                }
+                _ => (),
+            }
+        }
+
+        self.offset = self.source.len();
+        // A truncated source, so the value is closed at its end.
+        if self.lenient {
+            self.repair(ErrorKind::UnterminatedValue, start);
+
+            return Ok(Token {
+                kind: TokenKind::PropValue,
+                text: &self.source[content_start..],
+                span: Span {
+                    start,
+                    end: self.offset,
+                },
+            });
+        }
+
+        Err(self.error_at(ErrorKind::UnterminatedValue, start))
+    }
+}
+
+impl Lexer<'_> {
+    // Whether the "]" at `offset` belongs to the value rather than ending it: it isn't followed
+    // by anything that can follow a value, and another "]" comes before the next "[".
+    fn is_unescaped(&self, offset: usize) -> bool {
+        let rest = self.source[offset + 1..].trim_start();
+
+        let ends_value = match rest.chars().next() {
+            None => true,
+            Some(chars::TREE_START | chars::TREE_END | chars::NODE_START) => true,
+            Some(chars::PROP_VAL_START) => true,
+            Some('A'..='Z') => {
+                let ident = rest
+                    .find(|c: char| !c.is_ascii_alphabetic())
+                    .unwrap_or(rest.len());
+                rest[ident..]
+                    .trim_start()
+                    .starts_with(chars::PROP_VAL_START)
+            }
+            Some(_) => false,
+        };
+        if ends_value {
+            return false;
+        }
+
+        match (
+            rest.find(chars::PROP_VAL_END),
+            rest.find(chars::PROP_VAL_START),
+        ) {
+            (Some(end), Some(start)) => end < start,
+            (Some(_), None) => true,
+            _ => false,
+        }
+    }
+
+    fn repair(&mut self, kind: ErrorKind, offset: usize) {
+        let repair = self.error_at(kind, offset);
+        let repair = match self.node_index() {
+            Some(index) => repair.with_node(index),
+            None => repair,
+        };
+
+        self.repairs.push(repair);
+    }
+}
+
+fn starts_token(character: char) -> bool {
+    matches!(
+        character,
+        chars::TREE_START | chars::TREE_END | chars::NODE_START | chars::PROP_VAL_START | 'A'..='Z'
+    )
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Result<Token<'a>, SgfParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let token = match self.peeked.take() {
+            Some(peeked) => peeked,
+            None => self.lex_token(),
+        };
+
+        if let Some(Ok(Token {
+            kind: TokenKind::NodeStart,
+            ..
+        })) = token
+        {
+            self.node_count += 1;
+        }
+
+        token
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Lexer, Span, TokenKind};
+    use crate::errors::ErrorKind;
+
+    fn kinds(source: &str) -> Vec<TokenKind> {
+        Lexer::new(source)
+            .map(|token| token.unwrap().kind)
+            .collect()
+    }
+
+    #[test]
+    fn can_tokenize_game_tree() {
+        let content = "(;FF[4]AP[Primiview:3.1] (;B[pd]) )";
+
+        assert_eq!(
+            kinds(content),
+            vec![
+                TokenKind::TreeStart,
+                TokenKind::NodeStart,
+                TokenKind::PropIdent,
+                TokenKind::PropValue,
+                TokenKind::PropIdent,
+                TokenKind::PropValue,
+                TokenKind::TreeStart,
+                TokenKind::NodeStart,
+                TokenKind::PropIdent,
+                TokenKind::PropValue,
+                TokenKind::TreeEnd,
+                TokenKind::TreeEnd,
+            ]
+        );
+    }
+
+    #[test]
+    fn prop_value_text_excludes_brackets() {
+        let mut lexer = Lexer::new("AP[Primiview:3.1]");
+
+        let ident = lexer.next().unwrap().unwrap();
+        assert_eq!(ident.text, "AP");
+        assert_eq!(ident.span, Span { start: 0, end: 2 });
+
+        let value = lexer.next().unwrap().unwrap();
+        assert_eq!(value.text, "Primiview:3.1");
+        assert_eq!(value.span, Span { start: 2, end: 17 });
+    }
+
+    #[test]
+    fn spans_are_byte_offsets() {
+        let mut lexer = Lexer::new("PB[本因坊秀策]PW[이창호]");
+
+        lexer.next();
+        let black = lexer.next().unwrap().unwrap();
+        assert_eq!(black.text, "本因坊秀策");
+
+        let white_ident = lexer.next().unwrap().unwrap();
+        assert_eq!(white_ident.span.start, black.span.end);
+        assert_eq!(white_ident.text, "PW");
+
+        let white = lexer.next().unwrap().unwrap();
+        assert_eq!(white.text, "이창호");
+    }
+
+    #[test]
+    fn escaped_bracket_does_not_end_value() {
+        let mut lexer = Lexer::new(r"C[erased in FF[3\] way]");
+
+        lexer.next();
+        let value = lexer.next().unwrap().unwrap();
+        assert_eq!(value.text, r"erased in FF[3\] way");
+        assert!(lexer.next().is_none());
+    }
+
+    #[test]
+    fn unterminated_value_is_error() {
+        let mut lexer = Lexer::new("C[no end");
+
+        lexer.next();
+        let error = lexer.next().unwrap().unwrap_err();
+        assert_eq!(*error.kind(), ErrorKind::UnterminatedValue);
+        assert_eq!(error.position().unwrap().offset, 1);
+    }
+
+    #[test]
+    fn lenient_lexer_closes_unterminated_value() {
+        let mut lexer = Lexer::lenient("C[no end");
+
+        lexer.next();
+        assert_eq!(lexer.next().unwrap().unwrap().text, "no end");
+        assert!(lexer.next().is_none());
+
+        let repairs = lexer.take_repairs();
+        assert_eq!(*repairs[0].kind(), ErrorKind::UnterminatedValue);
+        assert_eq!(repairs[0].position().unwrap().offset, 1);
+    }
+
+    #[test]
+    fn unexpected_character_is_error() {
+        let mut lexer = Lexer::new(";?");
+
+        assert_eq!(lexer.next().unwrap().unwrap().kind, TokenKind::NodeStart);
+        let error = lexer.next().unwrap().unwrap_err();
+        assert_eq!(*error.kind(), ErrorKind::UnexpectedCharacter('?'));
+        assert_eq!(error.position().unwrap().column, 2);
+    }
+
+    #[test]
+    fn counts_consumed_nodes() {
+        let mut lexer = Lexer::new("(;B[aa];W[bb])");
+
+        assert_eq!(lexer.node_index(), None);
+        lexer.nth(3);
+        assert_eq!(lexer.node_index(), Some(0));
+        lexer.nth(2);
+        assert_eq!(lexer.node_index(), Some(1));
+    }
+
+    #[test]
+    fn lenient_lexer_keeps_unescaped_brackets() {
+        let mut lexer = Lexer::lenient("C[a [b] c]N[x]");
+
+        lexer.next();
+        assert_eq!(lexer.next().unwrap().unwrap().text, "a [b] c");
+        assert_eq!(lexer.next().unwrap().unwrap().text, "N");
+        assert_eq!(lexer.take_repairs().len(), 1);
+    }
+
+    #[test]
+    fn bracket_before_stray_text_ends_value() {
+        let mut lexer = Lexer::lenient("B[aa] x W[bb]");
+
+        lexer.next();
+        assert_eq!(lexer.next().unwrap().unwrap().text, "aa");
+        assert_eq!(lexer.next().unwrap().unwrap().text, "W");
+
+        let repairs = lexer.take_repairs();
+        assert_eq!(
+            *repairs[0].kind(),
+            ErrorKind::SkippedText(String::from("x"))
+        );
+    }
+}
@@ -1,43 +1,178 @@
 extern crate core;
 
+mod board;
+mod catalogue;
 mod chars;
+mod charset;
+mod codec;
 mod collection;
+mod cst;
+mod cursor;
+mod date;
 mod errors;
+mod game_result;
 mod game_tree;
+mod lexer;
+mod navigation;
 mod node;
+mod parser;
+mod point;
 mod property;
+mod rank;
+mod rules;
+mod scoring;
+mod text;
+mod timing;
+mod upgrade;
+mod writer;
 
-use std::{error::Error, fs, process};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
 
-use collection::Collection;
+pub use board::{replay, Board, BoardState, Prisoners};
+pub use codec::{Algebraic, Backgammon, Codecs, GameCodec, Go, Hex, Letters};
+pub use collection::Collection;
+pub use cst::{Cst, CstNode, CstProperty, CstTree};
+pub use cursor::Cursor;
+pub use date::PartialDate;
+pub use errors::{ErrorKind, Position, SgfParseError};
+pub use game_result::{GameResult, WinReason};
+pub use game_tree::GameTree;
+pub use lexer::TokenKind;
+pub use navigation::{BreadthFirst, DepthFirst, MainLine};
+pub use node::Node;
+pub use parser::{DuplicatePolicy, ParseOptions};
+pub use point::{BoardSize, Move, Point};
+pub use property::{Color, Property, PropertyType, PropertyValue};
+pub use rank::{Certainty, Rank, RankKind};
+pub use rules::{check, check_with, Diagnostic, KoRule, Ruleset, ScoringMethod, Violation};
+pub use scoring::{score, verify_result, ResultMismatch, Score};
+pub use timing::{Clock, Overtime, TimeControl};
+pub use writer::WriteOptions;
 
-pub fn parse(path: &str) -> Collection {
-    let contents = read_file(path).unwrap_or_else(|err| {
-        eprintln!("Error while reading file {}: {}", path, err);
-        process::exit(1);
-    });
+pub fn parse_str(source: &str) -> Result<Collection, SgfParseError> {
+    Collection::new(source)
+}
+
+pub fn parse_str_with(source: &str, options: ParseOptions) -> Result<Collection, SgfParseError> {
+    Collection::with_options(source, options)
+}
+
+// Reads the source in the charset given by CA, see `Collection::from_bytes`.
+pub fn parse_bytes(source: &[u8]) -> Result<Collection, SgfParseError> {
+    Collection::from_bytes(source)
+}
 
-    Collection::new(&contents).unwrap_or_else(|err| {
-        eprintln!("Error while parsing file {}: {}", path, err);
-        process::exit(1);
-    })
+pub fn parse_file<P: AsRef<Path>>(path: P) -> Result<Collection, SgfParseError> {
+    let contents = read_file(path)?;
+
+    parse_bytes(&contents)
 }
 
-fn read_file(path: &str) -> Result<String, Box<dyn Error>> {
-    let contents = fs::read_to_string(path)?;
+pub fn parse_reader<R: Read>(mut reader: R) -> Result<Collection, SgfParseError> {
+    let mut contents = vec![];
+    reader.read_to_end(&mut contents)?;
+
+    parse_bytes(&contents)
+}
+
+fn read_file<P: AsRef<Path>>(path: P) -> Result<Vec<u8>, SgfParseError> {
+    let contents = fs::read(path)?;
 
     Ok(contents)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::read_file;
+    use super::{parse_file, parse_reader, parse_str, read_file};
+    use crate::errors::ErrorKind;
+    use crate::point::{Move, Point};
+    use crate::property::PropertyValue;
 
     #[test]
     fn can_read_file() {
         let contents = read_file("resources/read_file.txt").unwrap();
 
         let expected = "Everything was beautiful,\nand nothing hurt.";
-        assert_eq!(expected, contents);
+        assert_eq!(expected.as_bytes(), contents);
+    }
+
+    #[test]
+    fn can_parse_str() {
+        let collection = parse_str("(;FF[4])(;FF[3])").unwrap();
+
+        assert_eq!(collection.game_trees().len(), 2);
+    }
+
+    #[test]
+    fn can_parse_reader() {
+        let collection = parse_reader("(;FF[4]AP[Primiview:3.1])".as_bytes()).unwrap();
+
+        assert_eq!(collection.game_trees().len(), 1);
+    }
+
+    #[test]
+    fn can_parse_example_file() {
+        let collection = parse_file("resources/example.sgf").unwrap();
+
+        assert_eq!(collection.game_trees().len(), 2);
+
+        let first = &collection.game_trees()[0];
+        assert_eq!(first.sequence().len(), 1);
+        assert_eq!(first.sequence()[0].properties.len(), 6);
+        assert_eq!(first.leaves().len(), 5);
+
+        let second = &collection.game_trees()[1];
+        assert_eq!(second.sequence().len(), 2);
+        assert_eq!(second.leaves().len(), 3);
+    }
+
+    #[test]
+    fn example_file_moves_have_coordinates() {
+        let collection = parse_file("resources/example.sgf").unwrap();
+        let moves = &collection.game_trees()[0].leaves()[0];
+
+        let black = &moves.sequence()[0].properties[0];
+        assert_eq!(black.id, "B");
+        assert_eq!(
+            black.values,
+            vec![PropertyValue::Move(Move::Play(Point::new(15, 3)))]
+        );
+
+        let white = &moves.sequence()[1].properties[0];
+        assert_eq!(white.id, "W");
+        assert_eq!(
+            white.values,
+            vec![PropertyValue::Move(Move::Play(Point::new(3, 15)))]
+        );
+    }
+
+    #[test]
+    fn example_file_comments_are_decoded() {
+        let collection = parse_file("resources/example.sgf").unwrap();
+        let style = &collection.game_trees()[0].leaves()[3].sequence()[0];
+
+        let comment = match &style.properties[2].values[0] {
+            PropertyValue::Text(comment) => comment,
+            other => panic!("Expected a comment, found {:?}", other),
+        };
+
+        assert!(comment.starts_with("There are hard linebreaks & soft linebreaks.\n"));
+        assert!(comment.contains("preceeded by '\\' like this one >ok<."));
+        assert_eq!(comment.matches(">ok<").count(), 6);
+    }
+
+    #[test]
+    fn missing_file_is_io_error() {
+        let error = parse_file("resources/does_not_exist.sgf").err().unwrap();
+
+        assert!(matches!(error.kind(), ErrorKind::Io(_)));
+    }
+
+    #[test]
+    fn invalid_source_is_error() {
+        assert!(parse_str("(;FF[4]").is_err());
+        assert!(parse_reader("(;FF[0])".as_bytes()).is_err());
     }
 }
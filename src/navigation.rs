@@ -0,0 +1,160 @@
+// Walking a GameTree as a tree of Nodes.
+
+// The children of a Node are the next Node of its sequence or, at the end of a sequence, the first
+// Node of every variation (leaf). A path is the list of child indices taken from the root, so the
+// root is `[]`, its main line child `[0]` and the second variation after it `[0, 1]`.
+
+use crate::game_tree::GameTree;
+use crate::node::Node;
+use std::collections::VecDeque;
+
+// A Node, identified by the GameTree whose sequence holds it and its index in that sequence.
+#[derive(Debug, Clone, Copy)]
+pub struct Location<'a> {
+    pub tree: &'a GameTree,
+    pub index: usize,
+}
+
+impl<'a> Location<'a> {
+    pub fn root(tree: &'a GameTree) -> Self {
+        Location { tree, index: 0 }
+    }
+
+    pub fn node(&self) -> &'a Node {
+        &self.tree.sequence()[self.index]
+    }
+
+    pub fn children(&self) -> Vec<Location<'a>> {
+        if self.index + 1 < self.tree.sequence().len() {
+            return vec![Location {
+                tree: self.tree,
+                index: self.index + 1,
+            }];
+        }
+
+        self.tree.leaves().iter().map(Location::root).collect()
+    }
+
+    pub fn child(&self, index: usize) -> Option<Location<'a>> {
+        if self.index + 1 < self.tree.sequence().len() {
+            return match index {
+                0 => Some(Location {
+                    tree: self.tree,
+                    index: self.index + 1,
+                }),
+                _ => None,
+            };
+        }
+
+        self.tree.leaves().get(index).map(Location::root)
+    }
+
+    pub fn is(&self, node: &Node) -> bool {
+        std::ptr::eq(self.node(), node)
+    }
+}
+
+// The locations from the root to `node`, both included; `None` if `node` isn't in `tree`.
+pub fn find<'a>(tree: &'a GameTree, node: &Node) -> Option<Vec<Location<'a>>> {
+    let mut trail = vec![];
+
+    if find_in(tree, node, &mut trail) {
+        Some(trail)
+    } else {
+        None
+    }
+}
+
+fn find_in<'a>(tree: &'a GameTree, node: &Node, trail: &mut Vec<Location<'a>>) -> bool {
+    let length = trail.len();
+
+    for index in 0..tree.sequence().len() {
+        let location = Location { tree, index };
+        trail.push(location);
+
+        if location.is(node) {
+            return true;
+        }
+    }
+
+    for leaf in tree.leaves() {
+        if find_in(leaf, node, trail) {
+            return true;
+        }
+    }
+
+    trail.truncate(length);
+    false
+}
+
+// Nodes in the order they are stored in the file, i.e. depth first.
+pub struct DepthFirst<'a> {
+    stack: Vec<Location<'a>>,
+}
+
+impl<'a> DepthFirst<'a> {
+    pub fn new(tree: &'a GameTree) -> Self {
+        DepthFirst {
+            stack: vec![Location::root(tree)],
+        }
+    }
+}
+
+impl<'a> Iterator for DepthFirst<'a> {
+    type Item = &'a Node;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let location = self.stack.pop()?;
+        self.stack.extend(location.children().into_iter().rev());
+
+        Some(location.node())
+    }
+}
+
+// Nodes level by level: the root, then all Nodes one move deep, and so on.
+pub struct BreadthFirst<'a> {
+    queue: VecDeque<Location<'a>>,
+}
+
+impl<'a> BreadthFirst<'a> {
+    pub fn new(tree: &'a GameTree) -> Self {
+        BreadthFirst {
+            queue: VecDeque::from([Location::root(tree)]),
+        }
+    }
+}
+
+impl<'a> Iterator for BreadthFirst<'a> {
+    type Item = &'a Node;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let location = self.queue.pop_front()?;
+        self.queue.extend(location.children());
+
+        Some(location.node())
+    }
+}
+
+// The root and then the first variation at every fork.
+pub struct MainLine<'a> {
+    next: Option<Location<'a>>,
+}
+
+impl<'a> MainLine<'a> {
+    pub fn new(tree: &'a GameTree) -> Self {
+        MainLine {
+            next: Some(Location::root(tree)),
+        }
+    }
+}
+
+impl<'a> Iterator for MainLine<'a> {
+    type Item = &'a Node;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let location = self.next?;
+        self.next = location.child(0);
+
+        Some(location.node())
+    }
+}
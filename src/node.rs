@@ -1,98 +1,419 @@
-use crate::chars;
-use crate::errors::SgfParseError;
+use crate::catalogue;
+use crate::codec::{self, GameCodec};
+use crate::errors::{ErrorKind, SgfParseError};
+use crate::lexer::TokenKind;
+use crate::parser::{DuplicatePolicy, Parser};
 // When numbering nodes starting with zero is suggested.
 // Nodes should be numbered in the way they are stored in the file.
 // Example (of file above): root=0, a=1, b=2, c=3, d=4, e=5, f=6, g=7, h=8, i=9 and j=10.
 // The order of properties in a node is not fixed. It may change every time the file is saved and
 // may vary from application to application.
 // Applications should not rely on the order of property values.
-use crate::property::Property;
+use crate::point::BoardSize;
+use crate::property::{Property, PropertyType, PropertyValue, RawProperty};
+use std::sync::Arc;
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct Node {
     pub properties: Vec<Property>,
 }
 
 impl Node {
-    pub fn parse(source: &str) -> Result<(Self, usize), SgfParseError> {
+    // The first property with the given identifier.
+    pub fn get(&self, id: &str) -> Option<&Property> {
+        self.properties.iter().find(|prop| prop.id == id)
+    }
+
+    pub fn to_sgf_with(&self, codec: &dyn GameCodec, size: BoardSize) -> String {
+        let mut sgf = String::from(";");
+
+        for property in &self.properties {
+            sgf.push_str(&property.to_sgf_with(codec, size));
+        }
+
+        sgf
+    }
+
+    pub fn parse(parser: &mut Parser) -> Result<Self, SgfParseError> {
         let mut properties: Vec<Property> = vec![];
+        let mut raw_properties: Vec<RawProperty> = vec![];
+
+        parser.lexer.expect(TokenKind::NodeStart)?;
+        let index = parser.lexer.node_index().unwrap_or_default();
+        let is_root = parser.take_root();
+        // The spelling of identifiers depends on FF, so it's checked once the root is read.
+        let mut unchecked: Vec<RawProperty> = vec![];
+
+        // The Node ends at the first token that can't start a Property, i.e. the next Node or a
+        // tree boundary.
+        while parser.lexer.peek_kind() == Some(TokenKind::PropIdent) {
+            let raw = Property::lex(parser).map_err(|err| err.with_node(index))?;
+            if is_root {
+                unchecked.push(raw.clone());
+            } else {
+                raw.check_spelling(parser)
+                    .map_err(|err| err.with_node(index))?;
+            }
+            Node::add_raw(parser, &mut raw_properties, raw).map_err(|err| err.with_node(index))?;
+        }
+
+        // Since property order is not fixed, the root node is read in full before decoding, so
+        // e.g. AB can come before the SZ it depends on.
+        if is_root {
+            parser.version =
+                Node::version(parser, &raw_properties).map_err(|err| err.with_node(index))?;
+            for raw in &unchecked {
+                raw.check_spelling(parser)
+                    .map_err(|err| err.with_node(index))?;
+            }
+            parser.codec =
+                Node::codec(parser, &raw_properties).map_err(|err| err.with_node(index))?;
+            parser.board_size =
+                Node::board_size(parser, &raw_properties).map_err(|err| err.with_node(index))?;
+        }
+
+        for raw in &raw_properties {
+            properties.push(Property::from_raw(parser, raw).map_err(|err| err.with_node(index))?);
+        }
+
+        Node::check_placement(parser, &raw_properties, is_root)
+            .map_err(|err| err.with_node(index))?;
+
+        Ok(Node { properties })
+    }
+
+    // Adds a property to the node, applying the duplicate policy if it's already there.
+    fn add_raw<'a>(
+        parser: &mut Parser,
+        raw_properties: &mut Vec<RawProperty<'a>>,
+        raw: RawProperty<'a>,
+    ) -> Result<(), SgfParseError> {
+        let first = match raw_properties
+            .iter_mut()
+            .find(|first| first.id() == raw.id())
+        {
+            Some(first) => first,
+            None => {
+                raw_properties.push(raw);
+                return Ok(());
+            }
+        };
+
+        let error = parser
+            .lexer
+            .error_at(ErrorKind::DuplicateProperty, raw.offset())
+            .with_property(raw.id());
+        match parser.options.duplicates {
+            DuplicatePolicy::Error => return Err(error),
+            DuplicatePolicy::KeepFirst => parser.warn(error),
+            DuplicatePolicy::Merge => {
+                parser.warn(error);
+                first.merge(raw);
+            }
+        }
 
-        let mut skip_counter = 0;
+        Ok(())
+    }
 
-        for (index, character) in source.chars().enumerate() {
-            let index = index + 1;
+    // Checks the restrictions of the property types, see `PropertyType`.
+    fn check_placement(
+        parser: &mut Parser,
+        raw_properties: &[RawProperty],
+        is_root: bool,
+    ) -> Result<(), SgfParseError> {
+        let of_type = |property_type| {
+            raw_properties.iter().filter(move |raw| {
+                catalogue::definition(raw.id())
+                    .is_some_and(|definition| definition.property_type == Some(property_type))
+            })
+        };
 
-            let _prop_id_buffer = String::new();
-            let _prop_val_buffer = String::new();
+        if !is_root {
+            for raw in of_type(PropertyType::Root) {
+                let error = parser
+                    .lexer
+                    .error_at(ErrorKind::MisplacedRootProperty, raw.offset());
+                parser.report(error.with_property(raw.id()))?;
+            }
+        }
 
-            if skip_counter > 0 {
-                skip_counter -= 1;
-                continue;
+        if of_type(PropertyType::Move).next().is_some() {
+            if let Some(raw) = of_type(PropertyType::Setup).next() {
+                let error = parser
+                    .lexer
+                    .error_at(ErrorKind::MixedMoveAndSetup, raw.offset());
+                parser.report(error.with_property(raw.id()))?;
             }
+        }
 
-            match character {
-                // White space (space, tab, carriage return, line feed, vertical tab and so on) may appear
-                // anywhere between PropValues, Properties, Nodes, Sequences and GameTrees.
-                ' ' | '\n' | '\t' => (),
-                chars::TREE_START => {
-                    // We have encountered a new new tree; this means the current Node is finished.
-                    return Ok((Node { properties }, index));
-                }
-                _ => {
-                    let remaining_content = source.split_at(index - 1);
-
-                    let prop_result = Property::parse(remaining_content.1)?;
-                    properties.push(prop_result.0);
-                    skip_counter = prop_result.1;
-                }
+        if let Some(raw) = of_type(PropertyType::GameInfo).next() {
+            if parser.game_info_seen {
+                let error = parser
+                    .lexer
+                    .error_at(ErrorKind::RepeatedGameInfo, raw.offset());
+                parser.report(error.with_property(raw.id()))?;
             }
+            parser.game_info_seen = true;
+        }
+
+        Ok(())
+    }
+
+    // The version given by FF; without one a file is FF[1].
+    fn version(parser: &mut Parser, raw_properties: &[RawProperty]) -> Result<u32, SgfParseError> {
+        let raw = match raw_properties.iter().find(|raw| raw.id() == "FF") {
+            Some(raw) => raw,
+            None => return Ok(1),
+        };
+
+        match Node::root_value(parser, raw)? {
+            Some(PropertyValue::Number(version, _, _)) => Ok(version),
+            _ => Ok(1),
+        }
+    }
+
+    // The codec for the game given by GM, Go if there is none.
+    fn codec(
+        parser: &mut Parser,
+        raw_properties: &[RawProperty],
+    ) -> Result<Option<Arc<dyn GameCodec>>, SgfParseError> {
+        let raw = match raw_properties.iter().find(|raw| raw.id() == "GM") {
+            Some(raw) => raw,
+            None => return Ok(parser.options.codecs.get(codec::GO)),
+        };
+
+        let game = match Node::root_value(parser, raw)? {
+            Some(PropertyValue::Number(game, _, _)) => game,
+            _ => codec::GO,
+        };
+
+        let codec = parser.options.codecs.get(game);
+        if codec.is_none() {
+            let warning = parser
+                .lexer
+                .error_at(ErrorKind::UnsupportedGame(game), raw.offset());
+            parser.warn(warning.with_property(raw.id()));
         }
 
-        Ok((Node { properties }, source.len()))
+        Ok(codec)
+    }
+
+    fn board_size(
+        parser: &mut Parser,
+        raw_properties: &[RawProperty],
+    ) -> Result<BoardSize, SgfParseError> {
+        let default = parser
+            .codec
+            .as_ref()
+            .map(|codec| codec.default_size())
+            .unwrap_or_default();
+
+        let raw = match raw_properties.iter().find(|raw| raw.id() == "SZ") {
+            Some(raw) => raw,
+            None => return Ok(default),
+        };
+
+        let size = Node::root_value(parser, raw)?
+            .as_ref()
+            .and_then(BoardSize::from_value)
+            .unwrap_or(default);
+
+        Ok(size)
+    }
+
+    // The first value of a root property the rest of the game depends on, `None` if it can't be
+    // decoded in lenient mode. It's decoded again with the rest of the node, which reports it.
+    fn root_value(
+        parser: &mut Parser,
+        raw: &RawProperty,
+    ) -> Result<Option<PropertyValue>, SgfParseError> {
+        match Property::decode_raw(parser, raw) {
+            Ok(property) => Ok(property.values.into_iter().next()),
+            Err(error) if parser.options.strict => Err(error),
+            Err(_) => Ok(None),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::errors::ErrorKind;
     use crate::node::Node;
+    use crate::parser::{DuplicatePolicy, ParseOptions, Parser};
+    use crate::point::{Move, Point};
     use crate::property::PropertyValue;
+    use test_case::test_case;
 
     #[test]
     fn can_parse_node_single_property() {
-        let content = "FF[4]";
-        let node = Node::parse(content).unwrap().0;
+        let content = ";FF[4]";
+        let node = Node::parse(&mut Parser::new(content)).unwrap();
 
         assert_eq!(node.properties.len(), 1);
 
-        let prop = node.properties.get(0).unwrap();
+        let prop = node.properties.first().unwrap();
 
         assert_eq!(prop.id, "FF");
     }
 
     #[test]
     fn can_parse_node_multiple_property() {
-        let content = "FF[1][2]FF[3]";
-        let node = Node::parse(content).unwrap().0;
+        let content = ";FF[4]SZ[19]GM[1]";
+        let node = Node::parse(&mut Parser::new(content)).unwrap();
 
-        assert_eq!(node.properties.len(), 2);
+        assert_eq!(node.properties.len(), 3);
 
-        let first_prop = node.properties.get(0).unwrap();
+        let first_prop = node.properties.first().unwrap();
         let second_prop = node.properties.get(1).unwrap();
+        let third_prop = node.properties.get(2).unwrap();
 
         assert_eq!(first_prop.id, "FF");
         assert_eq!(
-            *first_prop.values.get(0).unwrap(),
-            PropertyValue::Number(1, 1, 4)
+            *first_prop.values.first().unwrap(),
+            PropertyValue::Number(4, 1, 4)
         );
 
+        assert_eq!(second_prop.id, "SZ");
         assert_eq!(
-            *first_prop.values.get(1).unwrap(),
-            PropertyValue::Number(2, 1, 4)
+            *second_prop.values.first().unwrap(),
+            PropertyValue::Number(19, 1, 52)
         );
 
-        assert_eq!(second_prop.id, "FF");
+        assert_eq!(third_prop.id, "GM");
         assert_eq!(
-            *second_prop.values.get(0).unwrap(),
-            PropertyValue::Number(3, 1, 4)
+            *third_prop.values.first().unwrap(),
+            PropertyValue::Number(1, 1, u32::MAX)
         );
     }
+
+    #[test]
+    fn duplicate_property_is_error() {
+        let content = ";FF[4]SZ[19]FF[3]";
+        let error = Node::parse(&mut Parser::new(content)).err().unwrap();
+
+        assert_eq!(*error.kind(), ErrorKind::DuplicateProperty);
+        assert_eq!(error.property(), Some("FF"));
+        assert_eq!(error.position().unwrap().offset, 12);
+        assert_eq!(error.node(), Some(0));
+    }
+
+    #[test_case(DuplicatePolicy::KeepFirst, vec![PropertyValue::Stone(Point::new(0, 0))] ; "Keep first")]
+    #[test_case(DuplicatePolicy::Merge, vec![PropertyValue::Stone(Point::new(0, 0)), PropertyValue::Stone(Point::new(1, 1))] ; "Merge")]
+    fn duplicate_property_is_warning(duplicates: DuplicatePolicy, stones: Vec<PropertyValue>) {
+        let options = ParseOptions {
+            duplicates,
+            ..ParseOptions::default()
+        };
+        let mut parser = Parser::with_options(";AB[aa]C[first]AB[bb]C[second]", options);
+        let node = Node::parse(&mut parser).unwrap();
+
+        assert_eq!(node.properties.len(), 2);
+        assert_eq!(node.properties[0].values, stones);
+        // Single values can't be merged, so the first one is kept either way.
+        assert_eq!(
+            node.properties[1].values,
+            vec![PropertyValue::Text(String::from("first"))]
+        );
+
+        let warnings = parser.take_warnings();
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings
+            .iter()
+            .all(|warning| *warning.kind() == ErrorKind::DuplicateProperty));
+    }
+
+    #[test]
+    fn property_error_reports_node_index() {
+        let mut parser = Parser::new(";FF[4];FF[9]");
+        Node::parse(&mut parser).unwrap();
+
+        let error = Node::parse(&mut parser).err().unwrap();
+        assert_eq!(error.node(), Some(1));
+        assert_eq!(error.property(), Some("FF"));
+    }
+
+    #[test]
+    fn root_board_size_applies_regardless_of_order() {
+        let mut parser = Parser::new(";AB[hh]SZ[9];B[jj]");
+        let root = Node::parse(&mut parser).unwrap();
+
+        assert_eq!(
+            root.properties[0].values,
+            vec![PropertyValue::Stone(Point::new(7, 7))]
+        );
+
+        let error = Node::parse(&mut parser).err().unwrap();
+        assert_eq!(error.property(), Some("B"));
+        assert_eq!(error.node(), Some(1));
+    }
+
+    #[test]
+    fn can_parse_rectangular_board_size() {
+        let mut parser = Parser::new(";SZ[13:9];B[mi]");
+        Node::parse(&mut parser).unwrap();
+
+        let node = Node::parse(&mut parser).unwrap();
+        assert_eq!(
+            node.properties[0].values,
+            vec![PropertyValue::Move(Move::Play(Point::new(12, 8)))]
+        );
+    }
+
+    #[test]
+    fn can_parse_empty_node() {
+        let mut parser = Parser::new(";;V[4]");
+        let node = Node::parse(&mut parser).unwrap();
+
+        assert!(node.properties.is_empty());
+
+        let next = Node::parse(&mut parser).unwrap();
+        assert_eq!(next.properties.len(), 1);
+    }
+
+    #[test_case(";FF[4];SZ[9]", ErrorKind::MisplacedRootProperty, "SZ" ; "Root property outside root")]
+    #[test_case(";FF[4];B[aa]AW[bb]", ErrorKind::MixedMoveAndSetup, "AW" ; "Move and setup")]
+    fn misplaced_property_is_error(content: &str, kind: ErrorKind, property: &str) {
+        let mut parser = Parser::new(content);
+        Node::parse(&mut parser).unwrap();
+
+        let error = Node::parse(&mut parser).err().unwrap();
+        assert_eq!(*error.kind(), kind);
+        assert_eq!(error.property(), Some(property));
+        assert_eq!(error.node(), Some(1));
+    }
+
+    #[test]
+    fn misplaced_property_is_warning_in_lenient_mode() {
+        let options = ParseOptions {
+            strict: false,
+            ..ParseOptions::default()
+        };
+        let mut parser = Parser::with_options(";FF[4];SZ[9]B[aa]AE[bb]", options);
+        Node::parse(&mut parser).unwrap();
+
+        let node = Node::parse(&mut parser).unwrap();
+        assert_eq!(node.properties.len(), 3);
+
+        let kinds: Vec<ErrorKind> = parser
+            .take_warnings()
+            .iter()
+            .map(|warning| warning.kind().clone())
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ErrorKind::MisplacedRootProperty,
+                ErrorKind::MixedMoveAndSetup
+            ]
+        );
+    }
+
+    #[test]
+    fn properties_without_type_are_allowed_anywhere() {
+        let mut parser = Parser::new(";C[root]TR[aa];B[aa]C[move]TR[bb];AB[cc]LB[cc:x]");
+
+        for _ in 0..3 {
+            Node::parse(&mut parser).unwrap();
+        }
+    }
 }
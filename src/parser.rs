@@ -0,0 +1,146 @@
+// Shared state while parsing a Collection: the token stream and everything the parse functions
+// report along the way without failing, e.g. skipped unknown properties.
+
+use crate::codec::{Codecs, GameCodec, GO};
+use crate::errors::SgfParseError;
+use crate::lexer::Lexer;
+use crate::point::BoardSize;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseOptions {
+    // Whether violations of the spec that can be recovered from, e.g. a root property outside
+    // the root node, are errors. Otherwise they are reported as warnings, as are values that
+    // can't be decoded, which are kept as written.
+    // Lenient parsing also repairs malformed source where it can: text outside GameTrees and
+    // stray characters are skipped, unclosed GameTrees and values are closed, FF[4] identifiers
+    // are stripped of lowercase letters and unescaped "]" are kept in their values. Each repair
+    // is listed in `Collection::recoveries`.
+    pub strict: bool,
+    pub duplicates: DuplicatePolicy,
+    // The codecs points, moves and stones are decoded with, chosen by GM.
+    pub codecs: Codecs,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            strict: true,
+            duplicates: DuplicatePolicy::Error,
+            codecs: Codecs::default(),
+        }
+    }
+}
+
+// What to do with a property that appears more than once in a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    Error,
+    // Warn and keep the first one.
+    KeepFirst,
+    // Warn and append the values to the first one. Properties that only take a single value keep
+    // the first one.
+    Merge,
+}
+
+pub struct Parser<'a> {
+    pub lexer: Lexer<'a>,
+    pub options: ParseOptions,
+    warnings: Vec<SgfParseError>,
+    recoveries: Vec<SgfParseError>,
+    // Game specific values from the root node, needed to decode the rest of the game.
+    pub board_size: BoardSize,
+    // The FF version of the game.
+    pub version: u32,
+    // `None` if no codec is registered for the game.
+    pub codec: Option<Arc<dyn GameCodec>>,
+    at_root: bool,
+    // Whether a node on the path to the current one had game-info properties.
+    pub game_info_seen: bool,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(source: &'a str) -> Self {
+        Parser::with_options(source, ParseOptions::default())
+    }
+
+    pub fn with_options(source: &'a str, options: ParseOptions) -> Self {
+        let lexer = match options.strict {
+            true => Lexer::new(source),
+            false => Lexer::lenient(source),
+        };
+
+        Parser {
+            lexer,
+            warnings: vec![],
+            recoveries: vec![],
+            board_size: BoardSize::default(),
+            version: 4,
+            codec: options.codecs.get(GO),
+            at_root: true,
+            game_info_seen: false,
+            options,
+        }
+    }
+
+    // Called before each GameTree of a Collection, as every game has its own root node.
+    pub fn start_game(&mut self) {
+        self.board_size = BoardSize::default();
+        self.version = 4;
+        self.codec = self.options.codecs.get(GO);
+        self.at_root = true;
+        self.game_info_seen = false;
+    }
+
+    // Whether the next Node is the root node of a game; only true once per game.
+    pub fn take_root(&mut self) -> bool {
+        std::mem::replace(&mut self.at_root, false)
+    }
+
+    // Records a problem that was recovered from, tagged with the Node it occurred in.
+    pub fn warn(&mut self, warning: SgfParseError) {
+        let warning = match (warning.node(), self.lexer.node_index()) {
+            (None, Some(index)) => warning.with_node(index),
+            _ => warning,
+        };
+
+        self.warnings.push(warning);
+    }
+
+    // Fails with a violation of the spec in strict mode, and records it as a warning otherwise.
+    pub fn report(&mut self, violation: SgfParseError) -> Result<(), SgfParseError> {
+        if self.options.strict {
+            return Err(violation);
+        }
+
+        self.warn(violation);
+        Ok(())
+    }
+
+    // Fails with malformed source in strict mode, and records that it was repaired otherwise.
+    pub fn recover(&mut self, repair: SgfParseError) -> Result<(), SgfParseError> {
+        if self.options.strict {
+            return Err(repair);
+        }
+
+        let repair = match (repair.node(), self.lexer.node_index()) {
+            (None, Some(index)) => repair.with_node(index),
+            _ => repair,
+        };
+        self.recoveries.push(repair);
+        Ok(())
+    }
+
+    // The repairs made by the parser and the lexer, in source order.
+    pub fn take_recoveries(&mut self) -> Vec<SgfParseError> {
+        let mut recoveries = self.lexer.take_repairs();
+        recoveries.append(&mut self.recoveries);
+        recoveries.sort_by_key(|repair| repair.position().map(|position| position.offset));
+
+        recoveries
+    }
+
+    pub fn take_warnings(&mut self) -> Vec<SgfParseError> {
+        std::mem::take(&mut self.warnings)
+    }
+}
@@ -0,0 +1,319 @@
+// From the Go section of the SGF spec (https://www.red-bean.com/sgf/go.html):
+// The first letter designates the column (left to right), the second the row (top to bottom).
+// The upper left part of the board is used for smaller boards, e.g. letters "a"-"m" for 13*13.
+// Letters "a"-"z" are used for coordinates 0-25 and "A"-"Z" for 26-51.
+
+// A pass move is shown as '[]' or alternatively as '[tt]' (only for boards <= 19x19),
+// i.e. applications should be able to deal with both representations.
+// '[tt]' is kept for compatibility with FF[3].
+
+use crate::errors::{ErrorKind, SgfParseError};
+use crate::property::PropertyValue;
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Point {
+    // Column, counted from the left starting with zero.
+    pub x: u8,
+    // Row, counted from the top starting with zero.
+    pub y: u8,
+}
+
+// Go only places stones; the other variants are used by the codecs of other games.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Move {
+    Pass,
+    Play(Point),
+    // A piece moved from one point to another.
+    Step(Point, Point),
+    // A piece moved from one point to another, then shooting at a third, as in Amazons.
+    Shot(Point, Point, Point),
+    // Any other move, kept as written, e.g. castling in Chess.
+    Other(String),
+}
+
+// The board dimensions given by the SZ property; 19x19 unless stated otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BoardSize {
+    pub width: u8,
+    pub height: u8,
+}
+
+impl Default for BoardSize {
+    fn default() -> Self {
+        BoardSize {
+            width: 19,
+            height: 19,
+        }
+    }
+}
+
+impl BoardSize {
+    // Reads a decoded SZ value, either `number` or `number:number`.
+    pub fn from_value(value: &PropertyValue) -> Option<Self> {
+        match value {
+            PropertyValue::Number(size, _, _) => Some(BoardSize {
+                width: *size as u8,
+                height: *size as u8,
+            }),
+            PropertyValue::Compose(width, height) => match (&**width, &**height) {
+                (PropertyValue::Number(width, _, _), PropertyValue::Number(height, _, _)) => {
+                    Some(BoardSize {
+                        width: *width as u8,
+                        height: *height as u8,
+                    })
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        point.x < self.width && point.y < self.height
+    }
+}
+
+impl Point {
+    pub fn new(x: u8, y: u8) -> Self {
+        Point { x, y }
+    }
+
+    pub fn parse(val: &str, size: BoardSize) -> Result<Self, SgfParseError> {
+        let mut chars = val.chars();
+
+        let point = match (chars.next(), chars.next(), chars.next()) {
+            (Some(x), Some(y), None) => match (coordinate(x), coordinate(y)) {
+                (Some(x), Some(y)) => Point { x, y },
+                _ => return Err(invalid_point(val)),
+            },
+            _ => return Err(invalid_point(val)),
+        };
+
+        if !size.contains(point) {
+            return Err(SgfParseError::new(ErrorKind::InvalidValue(format!(
+                "Point '{}' is outside the {}x{} board",
+                val, size.width, size.height
+            ))));
+        }
+
+        Ok(point)
+    }
+
+    pub fn to_sgf(&self) -> String {
+        [letter(self.x), letter(self.y)].iter().collect()
+    }
+}
+
+impl Move {
+    pub fn parse(val: &str, size: BoardSize) -> Result<Self, SgfParseError> {
+        if val.is_empty() || (val == "tt" && size.width <= 19 && size.height <= 19) {
+            return Ok(Move::Pass);
+        }
+
+        Ok(Move::Play(Point::parse(val, size)?))
+    }
+}
+
+// From the SGF spec (https://www.red-bean.com/sgf/sgf4.html#3.5.1):
+// A compressed list of points is a rectangle given by its upper left and lower right points,
+// e.g. [do:gq]. It's illegal to specify a one point rectangle, e.g. [aa:aa].
+
+// Expands a rectangle into its points, row by row. The corners may be given in any order.
+pub fn expand_rectangle(first: Point, second: Point) -> Vec<Point> {
+    let (left, right) = (first.x.min(second.x), first.x.max(second.x));
+    let (top, bottom) = (first.y.min(second.y), first.y.max(second.y));
+
+    (top..=bottom)
+        .flat_map(|y| (left..=right).map(move |x| Point { x, y }))
+        .collect()
+}
+
+// Covers the points with rectangles, returned as (upper left, lower right) pairs; single points
+// are returned with both corners the same. Rectangles are grown greedily, first to the right and
+// then downwards, starting from the upper left most point not yet covered.
+pub fn compress_points(points: &[Point]) -> Vec<(Point, Point)> {
+    let mut remaining: BTreeSet<(u8, u8)> = points.iter().map(|point| (point.y, point.x)).collect();
+    let mut rectangles = vec![];
+
+    while let Some(&(top, left)) = remaining.iter().next() {
+        let mut right = left;
+        while right < u8::MAX && remaining.contains(&(top, right + 1)) {
+            right += 1;
+        }
+
+        let mut bottom = top;
+        while bottom < u8::MAX && (left..=right).all(|x| remaining.contains(&(bottom + 1, x))) {
+            bottom += 1;
+        }
+
+        for y in top..=bottom {
+            for x in left..=right {
+                remaining.remove(&(y, x));
+            }
+        }
+
+        rectangles.push((
+            Point { x: left, y: top },
+            Point {
+                x: right,
+                y: bottom,
+            },
+        ));
+    }
+
+    rectangles
+}
+
+fn coordinate(letter: char) -> Option<u8> {
+    match letter {
+        'a'..='z' => Some(letter as u8 - b'a'),
+        'A'..='Z' => Some(letter as u8 - b'A' + 26),
+        _ => None,
+    }
+}
+
+fn letter(coordinate: u8) -> char {
+    match coordinate {
+        0..=25 => (b'a' + coordinate) as char,
+        _ => (b'A' + coordinate - 26) as char,
+    }
+}
+
+fn invalid_point(val: &str) -> SgfParseError {
+    SgfParseError::new(ErrorKind::InvalidValue(format!("'{}' is not a point", val)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compress_points, expand_rectangle, BoardSize, Move, Point};
+    use test_case::test_case;
+
+    #[test_case("aa", 0, 0 ; "Top left")]
+    #[test_case("pd", 15, 3 ; "Upper right star point")]
+    #[test_case("dp", 3, 15 ; "Lower left star point")]
+    #[test_case("ss", 18, 18 ; "Bottom right")]
+    fn can_parse_point(val: &str, x: u8, y: u8) {
+        let point = Point::parse(val, BoardSize::default()).unwrap();
+
+        assert_eq!(point, Point::new(x, y));
+        assert_eq!(point.to_sgf(), val);
+    }
+
+    #[test]
+    fn can_parse_upper_case_coordinates() {
+        let size = BoardSize {
+            width: 52,
+            height: 52,
+        };
+        let point = Point::parse("zA", size).unwrap();
+
+        assert_eq!(point, Point::new(25, 26));
+        assert_eq!(point.to_sgf(), "zA");
+    }
+
+    #[test_case("" ; "Empty")]
+    #[test_case("a" ; "Single letter")]
+    #[test_case("abc" ; "Three letters")]
+    #[test_case("a1" ; "Digit")]
+    #[test_case("tt" ; "Outside the board")]
+    fn invalid_point_is_error(val: &str) {
+        assert!(Point::parse(val, BoardSize::default()).is_err());
+    }
+
+    #[test]
+    fn point_must_fit_board_size() {
+        let size = BoardSize {
+            width: 9,
+            height: 13,
+        };
+
+        assert!(Point::parse("hm", size).is_ok());
+        assert!(Point::parse("jm", size).is_err());
+        assert!(Point::parse("hn", size).is_err());
+    }
+
+    #[test_case("", BoardSize::default() ; "Empty")]
+    #[test_case("tt", BoardSize::default() ; "FF3 pass")]
+    #[test_case("tt", BoardSize { width: 9, height: 9 } ; "FF3 pass on small board")]
+    fn can_parse_pass(val: &str, size: BoardSize) {
+        assert_eq!(Move::parse(val, size).unwrap(), Move::Pass);
+    }
+
+    #[test]
+    fn tt_is_a_point_on_large_boards() {
+        let size = BoardSize {
+            width: 21,
+            height: 21,
+        };
+
+        assert_eq!(
+            Move::parse("tt", size).unwrap(),
+            Move::Play(Point::new(19, 19))
+        );
+    }
+
+    #[test]
+    fn can_expand_rectangle() {
+        let points = expand_rectangle(Point::new(3, 14), Point::new(6, 16));
+
+        assert_eq!(points.len(), 12);
+        assert_eq!(points.first(), Some(&Point::new(3, 14)));
+        assert_eq!(points.last(), Some(&Point::new(6, 16)));
+        assert!(points.contains(&Point::new(5, 15)));
+    }
+
+    #[test]
+    fn can_expand_rectangle_with_swapped_corners() {
+        let points = expand_rectangle(Point::new(1, 1), Point::new(0, 0));
+
+        assert_eq!(
+            points,
+            vec![
+                Point::new(0, 0),
+                Point::new(1, 0),
+                Point::new(0, 1),
+                Point::new(1, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn compress_is_inverse_of_expand() {
+        let points = expand_rectangle(Point::new(10, 13), Point::new(11, 16));
+
+        assert_eq!(
+            compress_points(&points),
+            vec![(Point::new(10, 13), Point::new(11, 16))]
+        );
+    }
+
+    #[test]
+    fn can_compress_mixed_points() {
+        // TW[pq:ss][so][lr:ns] from example.sgf, listed point by point.
+        let mut points = expand_rectangle(Point::new(15, 16), Point::new(18, 18));
+        points.push(Point::new(18, 14));
+        points.extend(expand_rectangle(Point::new(11, 17), Point::new(13, 18)));
+
+        let rectangles = compress_points(&points);
+
+        assert_eq!(
+            rectangles,
+            vec![
+                (Point::new(18, 14), Point::new(18, 14)),
+                (Point::new(15, 16), Point::new(18, 18)),
+                (Point::new(11, 17), Point::new(13, 18)),
+            ]
+        );
+    }
+
+    #[test]
+    fn compress_ignores_duplicates() {
+        let points = vec![Point::new(0, 0), Point::new(1, 0), Point::new(0, 0)];
+
+        assert_eq!(
+            compress_points(&points),
+            vec![(Point::new(0, 0), Point::new(1, 0))]
+        );
+    }
+}
@@ -15,24 +15,35 @@
 // Each property has a property type. Property types place restrictions on certain properties,
 // e.g. in which nodes they are allowed and with which properties they may be combined.
 
-use crate::chars;
-use crate::errors::SgfParseError;
-use std::error::Error;
+use crate::catalogue::{self, PropertyDefinition, ValueCount, ValueType};
+use crate::codec::GameCodec;
+use crate::date::{self, PartialDate};
+use crate::errors::{ErrorKind, SgfParseError};
+use crate::game_result::GameResult;
+use crate::lexer::{Token, TokenKind};
+use crate::parser::Parser;
+use crate::point::{self, BoardSize, Move, Point};
+use crate::rank::Rank;
+use crate::text;
+use crate::timing::Overtime;
 use std::fmt::Debug;
 
-enum PropertyType {
+// Move properties may not be mixed with setup properties in one node. Root properties may only
+// appear in the root node, and game-info properties only once on any path through a game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PropertyType {
     Move,
     Setup,
     Root,
     GameInfo,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum PropertyValue {
     None,
     // Number with a range.
     Number(u32, u32, u32),
-    Real(String),
+    Real(f64),
     // Double values are used for annotation properties.
     // They are called Double because the value is either simple or emphasized.
     // A value of '1' means 'normal'; '2' means that it is emphasized.
@@ -63,151 +74,499 @@ pub enum PropertyValue {
     Text(String),
 
     // The rest of these are game specific.
-    Point,
-    Move,
-    Stone,
+    Point(Point),
+    Move(Move),
+    Stone(Point),
     Compose(Box<PropertyValue>, Box<PropertyValue>),
+
+    // The value of RE.
+    Result(GameResult),
+    // The dates of DT, with shortcuts resolved.
+    Date(Vec<PartialDate>),
+    // The value of OT.
+    Overtime(Overtime),
+    // The value of BR and WR.
+    Rank(Rank),
+
+    // The raw value of an unknown or private property, with escapes left untouched.
+    Unknown(String),
 }
 
 impl PropertyValue {
+    // The value as it is written between the brackets of a property, with escapes applied.
+    pub fn to_sgf_with(&self, codec: &dyn GameCodec, size: BoardSize) -> String {
+        self.encode(false, codec, size)
+    }
+
+    fn encode(&self, in_compose: bool, codec: &dyn GameCodec, size: BoardSize) -> String {
+        match self {
+            PropertyValue::None => String::new(),
+            PropertyValue::Number(val, _, _) => val.to_string(),
+            PropertyValue::Real(val) => val.to_string(),
+            PropertyValue::Double(emphasized) => String::from(if *emphasized { "2" } else { "1" }),
+            PropertyValue::Color(Color::Black) => String::from("B"),
+            PropertyValue::Color(Color::White) => String::from("W"),
+            PropertyValue::SimpleText(val) | PropertyValue::Text(val) => {
+                text::escape(val, in_compose)
+            }
+            PropertyValue::Point(point) | PropertyValue::Stone(point) => {
+                codec.point_to_sgf(*point, size)
+            }
+            PropertyValue::Move(played) => codec.move_to_sgf(played, size),
+            PropertyValue::Compose(first, second) => format!(
+                "{}:{}",
+                first.encode(true, codec, size),
+                second.encode(true, codec, size)
+            ),
+            PropertyValue::Result(result) => text::escape(&result.to_sgf(), in_compose),
+            PropertyValue::Date(dates) => date::format_dates(dates),
+            PropertyValue::Overtime(overtime) => text::escape(&overtime.to_sgf(), in_compose),
+            PropertyValue::Rank(rank) => text::escape(&rank.to_sgf(), in_compose),
+            PropertyValue::Unknown(raw) => raw.clone(),
+        }
+    }
+
     fn validate(&self) -> Result<(), SgfParseError> {
         match self {
             PropertyValue::None => Ok(()),
             PropertyValue::Number(val, min, max) => {
                 if val < min || val > max {
-                    Err(SgfParseError::new(format!(
+                    Err(SgfParseError::new(ErrorKind::InvalidValue(format!(
                         "Value {} not in range (min {}, max {})",
                         val, min, max
-                    )))
+                    ))))
                 } else {
                     Ok(())
                 }
             }
-            PropertyValue::Real(val) => Ok(()),
-            PropertyValue::Double(val) => Ok(()),
-            PropertyValue::Color(val) => Ok(()),
-            PropertyValue::SimpleText(val) => Ok(()),
-            PropertyValue::Text(val) => Ok(()),
-            PropertyValue::Point => Ok(()),
-            PropertyValue::Move => Ok(()),
-            PropertyValue::Stone => Ok(()),
-            PropertyValue::Compose(val_1, val_2) => Ok(()),
+            PropertyValue::Real(_) => Ok(()),
+            PropertyValue::Double(_) => Ok(()),
+            PropertyValue::Color(_) => Ok(()),
+            PropertyValue::SimpleText(_) => Ok(()),
+            PropertyValue::Text(_) => Ok(()),
+            PropertyValue::Point(_) => Ok(()),
+            PropertyValue::Move(_) => Ok(()),
+            PropertyValue::Stone(_) => Ok(()),
+            PropertyValue::Compose(val_1, val_2) => {
+                val_1.validate()?;
+                val_2.validate()
+            }
+            PropertyValue::Result(_) => Ok(()),
+            PropertyValue::Date(_) => Ok(()),
+            PropertyValue::Overtime(_) => Ok(()),
+            PropertyValue::Rank(_) => Ok(()),
+            PropertyValue::Unknown(_) => Ok(()),
         }
     }
 }
 
-impl PartialEq for PropertyValue {
-    fn eq(&self, other: &Self) -> bool {
-        match self {
-            PropertyValue::Number(a, b, c) => {
-                if let PropertyValue::Number(x, y, z) = other {
-                    return a == x && b == y && c == z;
-                };
+// A Property as it appears in the source, before its values are decoded.
+#[derive(Clone)]
+pub struct RawProperty<'a> {
+    ident: Token<'a>,
+    // The identifier without lowercase letters.
+    id: String,
+    values: Vec<Token<'a>>,
+}
 
-                todo!()
-            }
-            _ => todo!(),
+impl<'a> RawProperty<'a> {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    // The byte offset of the identifier in the source.
+    pub fn offset(&self) -> usize {
+        self.ident.span.start
+    }
+
+    // Before FF[4] identifiers could contain lowercase letters, which are to be ignored, e.g.
+    // "AddBlack" is AB. Since FF[4] they are malformed.
+    pub fn check_spelling(&self, parser: &mut Parser) -> Result<(), SgfParseError> {
+        if self.id == self.ident.text || parser.version < 4 {
+            return Ok(());
+        }
+
+        let repair = parser.lexer.error_at(
+            ErrorKind::LowercaseIdentifier(String::from(self.ident.text)),
+            self.offset(),
+        );
+        parser.recover(repair.with_property(&self.id))
+    }
+
+    // Appends the values of a duplicate, unless this property only takes a single value.
+    pub fn merge(&mut self, duplicate: RawProperty<'a>) {
+        let single = catalogue::definition(self.id())
+            .is_some_and(|definition| definition.count == ValueCount::Single);
+
+        if !single {
+            self.values.extend(duplicate.values);
         }
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct Property {
     pub id: String,
+    // Empty for an `elist` property given as `[]`.
     pub values: Vec<PropertyValue>,
 }
 
 impl Property {
-    pub fn parse(source: &str) -> Result<(Self, usize), SgfParseError> {
-        let mut parse_mode = PropParseMode::ID;
-        let mut prop_id = String::new();
+    pub fn parse(parser: &mut Parser) -> Result<Self, SgfParseError> {
+        let raw = Property::lex(parser)?;
+        raw.check_spelling(parser)?;
+
+        Property::from_raw(parser, &raw)
+    }
+
+    // Reads the identifier and values of a Property, without decoding the values yet.
+    pub fn lex<'a>(parser: &mut Parser<'a>) -> Result<RawProperty<'a>, SgfParseError> {
+        let ident = parser.lexer.expect(TokenKind::PropIdent)?;
         let mut values = vec![];
-        let mut prop_id_buffer = String::new();
-        let mut prop_val_buffer = String::new();
-
-        for (index, character) in source.chars().enumerate() {
-            let index = index + 1;
-
-            match character {
-                chars::PROP_VAL_START => {
-                    // Property values are starting.
-                    // Properties have only one ID, so we are done with the prop_id_buffer.
-                    parse_mode = PropParseMode::Value;
-                    prop_id = prop_id_buffer.clone();
-                }
-                chars::PROP_VAL_END => {
-                    let val = prop_val_buffer.as_str();
-                    let prop_val = Property::get_prop_val(prop_id.as_str(), val)?;
-                    values.push(prop_val);
-                    prop_val_buffer.clear();
+
+        // Lowercase letters are left out of the identifier, see `RawProperty::check_spelling`.
+        let id: String = ident
+            .text
+            .chars()
+            .filter(char::is_ascii_uppercase)
+            .collect();
+
+        // Every Property has at least one value; any further values follow directly after it.
+        loop {
+            let value = parser
+                .lexer
+                .expect(TokenKind::PropValue)
+                .map_err(|err| err.with_property(&id))?;
+            values.push(value);
+
+            if parser.lexer.peek_kind() != Some(TokenKind::PropValue) {
+                break;
+            }
+        }
+
+        Ok(RawProperty { ident, id, values })
+    }
+
+    // Values that can't be decoded are an error in strict mode. Otherwise they are reported as a
+    // warning and kept as written.
+    pub fn from_raw(parser: &mut Parser, raw: &RawProperty) -> Result<Self, SgfParseError> {
+        match Property::decode_raw(parser, raw) {
+            Ok(property) => Ok(property),
+            Err(error) => {
+                parser.report(error)?;
+
+                Ok(Property {
+                    id: String::from(raw.id()),
+                    values: raw
+                        .values
+                        .iter()
+                        .map(|value| PropertyValue::Unknown(String::from(value.text)))
+                        .collect(),
+                })
+            }
+        }
+    }
+
+    // Like `from_raw`, but fails on any value that can't be decoded.
+    pub fn decode_raw(parser: &mut Parser, raw: &RawProperty) -> Result<Self, SgfParseError> {
+        let prop_id = raw.id();
+
+        let definition = match catalogue::definition_for(prop_id, parser.version) {
+            Some(definition) => definition,
+            None => {
+                // Unknown and private properties are kept verbatim, so they survive a round-trip.
+                parser.warn(
+                    parser
+                        .lexer
+                        .error_at(ErrorKind::UnknownProperty, raw.ident.span.start)
+                        .with_property(prop_id),
+                );
+
+                return Ok(Property {
+                    id: String::from(prop_id),
+                    values: raw
+                        .values
+                        .iter()
+                        .map(|value| PropertyValue::Unknown(String::from(value.text)))
+                        .collect(),
+                });
+            }
+        };
+
+        let codec = parser.codec.clone();
+        let mut values = vec![];
+        for value in &raw.values {
+            let prop_val = Property::get_prop_val(
+                &definition,
+                value.text,
+                codec.as_deref(),
+                parser.board_size,
+                parser.options.strict,
+            )
+            .map_err(|err| {
+                err.with_property(prop_id)
+                    .with_position(parser.lexer.position_at(value.span.start))
+            })?;
+
+            // Dates not in the ISO format are kept as text, e.g. DT[2004.03.13].
+            if definition.value_type == catalogue::DATE_OR_TEXT
+                && matches!(prop_val, PropertyValue::SimpleText(_))
+            {
+                let details = format!("'{}' is not an ISO date", value.text);
+                parser.warn(
+                    SgfParseError::new(ErrorKind::InvalidValue(details))
+                        .with_property(prop_id)
+                        .with_position(parser.lexer.position_at(value.span.start)),
+                );
+            }
+            // Results that aren't recognised are kept as text, e.g. RE[Unfinished].
+            if definition.value_type == catalogue::RESULT_OR_TEXT
+                && matches!(prop_val, PropertyValue::SimpleText(_))
+            {
+                let details = format!("'{}' is not a result", value.text);
+                parser.warn(
+                    SgfParseError::new(ErrorKind::InvalidValue(details))
+                        .with_property(prop_id)
+                        .with_position(parser.lexer.position_at(value.span.start)),
+                );
+            }
+            values.push(prop_val);
+        }
+
+        let raw_texts: Vec<&str> = raw.values.iter().map(|value| value.text).collect();
+        let values = Property::check_count(&definition, values, &raw_texts).map_err(|err| {
+            err.with_property(prop_id)
+                .with_position(parser.lexer.position_at(raw.ident.span.start))
+        })?;
+
+        Ok(Property {
+            id: String::from(prop_id),
+            values,
+        })
+    }
+
+    pub fn to_sgf_with(&self, codec: &dyn GameCodec, size: BoardSize) -> String {
+        let mut sgf = self.id.clone();
+
+        if self.values.is_empty() {
+            sgf.push_str("[]");
+        }
+        for value in &self.values {
+            sgf.push_str(&format!("[{}]", value.to_sgf_with(codec, size)));
+        }
+
+        sgf
+    }
+
+    // `None` for unknown properties and those without a type.
+    pub fn property_type(&self) -> Option<PropertyType> {
+        catalogue::definition(&self.id).and_then(|definition| definition.property_type)
+    }
+
+    // Whether this is a list of points that may be compressed, e.g. AB, TR or VW.
+    pub fn is_point_list(&self) -> bool {
+        catalogue::definition(&self.id).is_some_and(|definition| definition.is_point_list())
+    }
+
+    // All points of a point list property, with compressed rectangles expanded.
+    // Empty for any other property.
+    pub fn points(&self) -> Vec<Point> {
+        let mut points = vec![];
+
+        if !self.is_point_list() {
+            return points;
+        }
+
+        for value in &self.values {
+            match value {
+                PropertyValue::Point(point) | PropertyValue::Stone(point) => points.push(*point),
+                PropertyValue::Compose(first, second) => {
+                    if let (
+                        PropertyValue::Point(first) | PropertyValue::Stone(first),
+                        PropertyValue::Point(second) | PropertyValue::Stone(second),
+                    ) = (&**first, &**second)
+                    {
+                        points.extend(point::expand_rectangle(*first, *second));
+                    }
                 }
-                chars::TREE_START => {
-                    // We have encountered a new new tree; this means the current Property is finished.
-                    return Ok((
-                        Property {
-                            id: prop_id,
-                            values,
-                        },
-                        index - 2,
-                    ));
+                _ => (),
+            }
+        }
+
+        points
+    }
+
+    // Builds a point list property, compressing the points into rectangles where possible.
+    pub fn from_points(id: &str, points: &[Point]) -> Result<Self, SgfParseError> {
+        let definition = catalogue::definition(id)
+            .filter(|definition| definition.is_point_list())
+            .ok_or_else(|| {
+                invalid_value(format!("{} is not a list of points", id)).with_property(id)
+            })?;
+
+        if points.is_empty() && definition.count != ValueCount::EList {
+            return Err(
+                invalid_value(String::from("Expected at least one point")).with_property(id)
+            );
+        }
+
+        let wrap = |point: Point| match definition.value_type {
+            ValueType::Stone => PropertyValue::Stone(point),
+            _ => PropertyValue::Point(point),
+        };
+
+        let values = point::compress_points(points)
+            .into_iter()
+            .map(|(first, second)| {
+                if first == second {
+                    wrap(first)
+                } else {
+                    PropertyValue::Compose(Box::new(wrap(first)), Box::new(wrap(second)))
                 }
-                // White space (space, tab, carriage return, line feed, vertical tab and so on) may appear
-                // anywhere between PropValues, Properties, Nodes, Sequences and GameTrees.
-                ' ' | '\n' | '\t' => (),
-                other => {
-                    if index >= 2 && source.chars().nth(index - 2).unwrap() == chars::PROP_VAL_END {
-                        return Ok((
-                            Property {
-                                id: prop_id,
-                                values,
-                            },
-                            index - 2,
-                        ));
-                    }
-                    match parse_mode {
-                        PropParseMode::ID => prop_id_buffer.push(other),
-                        PropParseMode::Value => prop_val_buffer.push(other),
-                    };
+            })
+            .collect();
+
+        Ok(Property {
+            id: String::from(id),
+            values,
+        })
+    }
+
+    // Rewrites the values of a point list property into as few rectangles as possible.
+    pub fn compress(&mut self) -> Result<(), SgfParseError> {
+        let compressed = Property::from_points(&self.id, &self.points())?;
+        self.values = compressed.values;
+
+        Ok(())
+    }
+
+    fn check_count(
+        definition: &PropertyDefinition,
+        values: Vec<PropertyValue>,
+        raw_values: &[&str],
+    ) -> Result<Vec<PropertyValue>, SgfParseError> {
+        match definition.count {
+            ValueCount::Single if values.len() > 1 => Err(SgfParseError::new(
+                ErrorKind::InvalidValue(format!("Expected one value, found {}", values.len())),
+            )),
+            ValueCount::EList if raw_values == [""] => Ok(vec![]),
+            ValueCount::EList if raw_values.contains(&"") => Err(SgfParseError::new(
+                ErrorKind::InvalidValue(String::from("An empty value must be the only value")),
+            )),
+            _ => Ok(values),
+        }
+    }
+
+    fn get_prop_val(
+        definition: &PropertyDefinition,
+        val: &str,
+        codec: Option<&dyn GameCodec>,
+        size: BoardSize,
+        strict: bool,
+    ) -> Result<PropertyValue, SgfParseError> {
+        // An empty `elist` is checked as a whole in `check_count`.
+        if definition.count == ValueCount::EList && val.is_empty() {
+            return Ok(PropertyValue::None);
+        }
+
+        // Point lists may be compressed into a rectangle of two points, e.g. AB[do:gq].
+        // Other composed values, e.g. AP[Primiview:3.1], are handled by `decode`.
+        if definition.is_point_list() && val.contains(':') {
+            let value_type = match definition.value_type {
+                ValueType::Stone => ValueType::Compose(&ValueType::Stone, &ValueType::Stone),
+                _ => ValueType::Compose(&ValueType::Point, &ValueType::Point),
+            };
+
+            let rectangle = Property::decode(&value_type, val, codec, size, strict)?;
+            if let PropertyValue::Compose(first, second) = &rectangle {
+                if first == second {
+                    return Err(invalid_value(format!("'{}' is a one point rectangle", val)));
                 }
             }
+
+            return Ok(rectangle);
         }
 
-        return Ok((
-            Property {
-                id: prop_id,
-                values,
-            },
-            source.len(),
-        ));
+        Property::decode(&definition.value_type, val, codec, size, strict)
     }
 
-    fn get_prop_val(id: &str, val: &str) -> Result<PropertyValue, SgfParseError> {
-        let prop_val = match id {
-            "FF" => {
-                let converted = match val.parse::<u32>() {
-                    Ok(x) => x,
-                    Err(err) => Err(SgfParseError::new(err.to_string()))?,
-                };
-                PropertyValue::Number(converted, 1, 4)
+    // Points, moves and stones are decoded by the codec of the game; without one they are kept
+    // as written. Results are only normalised when not strict, e.g. "b+res".
+    fn decode(
+        value_type: &ValueType,
+        val: &str,
+        codec: Option<&dyn GameCodec>,
+        size: BoardSize,
+        strict: bool,
+    ) -> Result<PropertyValue, SgfParseError> {
+        let prop_val = match value_type {
+            ValueType::None => {
+                if !val.is_empty() {
+                    return Err(invalid_value(format!("Expected no value, found '{}'", val)));
+                }
+                PropertyValue::None
             }
-            "AP" => {
-                let split: Vec<&str> = val.split(':').collect();
+            ValueType::Number(min, max) => PropertyValue::Number(parse_number(val)?, *min, *max),
+            ValueType::Real => PropertyValue::Real(parse_real(val)?),
+            ValueType::Double => match val {
+                "1" => PropertyValue::Double(false),
+                "2" => PropertyValue::Double(true),
+                _ => return Err(invalid_value(format!("'{}' is not a double", val))),
+            },
+            ValueType::Color => match val {
+                "B" => PropertyValue::Color(Color::Black),
+                "W" => PropertyValue::Color(Color::White),
+                _ => return Err(invalid_value(format!("'{}' is not a color", val))),
+            },
+            ValueType::SimpleText => PropertyValue::SimpleText(text::decode_simple_text(val)),
+            ValueType::Text => PropertyValue::Text(text::decode_text(val)),
+            ValueType::Point | ValueType::Move | ValueType::Stone => {
+                let codec = match codec {
+                    Some(codec) => codec,
+                    None => return Ok(PropertyValue::Unknown(String::from(val))),
+                };
 
-                if split.len() < 2 {
-                    Err(SgfParseError::new(String::from(
-                        "Invalid composite value for AP.",
-                    )))?
+                match value_type {
+                    ValueType::Point => PropertyValue::Point(codec.parse_point(val, size)?),
+                    ValueType::Move => PropertyValue::Move(codec.parse_move(val, size)?),
+                    _ => PropertyValue::Stone(codec.parse_point(val, size)?),
                 }
+            }
+            ValueType::Result => {
+                let val = text::decode_simple_text(val);
+                PropertyValue::Result(if strict {
+                    GameResult::parse(&val)?
+                } else {
+                    GameResult::parse_lenient(&val)?
+                })
+            }
+            ValueType::Date => {
+                PropertyValue::Date(date::parse_dates(&text::decode_simple_text(val))?)
+            }
+            ValueType::Overtime => {
+                PropertyValue::Overtime(Overtime::parse(&text::decode_simple_text(val)))
+            }
+            ValueType::Rank => PropertyValue::Rank(Rank::parse(&text::decode_simple_text(val))?),
+            ValueType::Compose(first, second) => {
+                let (first_val, second_val) = text::split_compose(val)
+                    .ok_or_else(|| invalid_value(format!("'{}' is not a composed value", val)))?;
 
                 PropertyValue::Compose(
-                    Box::new(PropertyValue::SimpleText(String::from(
-                        *split.get(0).unwrap(),
-                    ))),
-                    Box::new(PropertyValue::SimpleText(String::from(
-                        *split.get(1).unwrap(),
-                    ))),
+                    Box::new(Property::decode(first, first_val, codec, size, strict)?),
+                    Box::new(Property::decode(second, second_val, codec, size, strict)?),
                 )
             }
-            _ => todo!(),
+            ValueType::Or(first, second) => {
+                return Property::decode(first, val, codec, size, strict).or_else(
+                    |err| match first {
+                        // Dates in the ISO format are errors when out of range, e.g. "2004-13-01".
+                        ValueType::Date if date::is_iso_form(val) => Err(err),
+                        // Numbers are errors when out of range, e.g. SZ[0], rather than composed values.
+                        ValueType::Number(..) if !val.contains(':') => Err(err),
+                        _ => Property::decode(second, val, codec, size, strict),
+                    },
+                );
+            }
         };
 
         prop_val.validate()?;
@@ -215,35 +574,72 @@ impl Property {
     }
 }
 
-enum PropParseMode {
-    ID,
-    Value,
+fn invalid_value(details: String) -> SgfParseError {
+    SgfParseError::new(ErrorKind::InvalidValue(details))
+}
+
+// Number: [+|-] Digit {Digit}
+fn parse_number(val: &str) -> Result<u32, SgfParseError> {
+    val.parse::<u32>()
+        .map_err(|err| invalid_value(format!("'{}' is not a number: {}", val, err)))
+}
+
+// Real: Number ["." Digit { Digit }]
+fn parse_real(val: &str) -> Result<f64, SgfParseError> {
+    let unsigned = val.strip_prefix(['+', '-']).unwrap_or(val);
+    let (whole, fraction) = unsigned.split_once('.').unwrap_or((unsigned, "0"));
+
+    let is_digits = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit());
+    if !is_digits(whole) || !is_digits(fraction) {
+        return Err(invalid_value(format!("'{}' is not a real number", val)));
+    }
+
+    val.parse::<f64>()
+        .map_err(|err| invalid_value(format!("'{}' is not a real number: {}", val, err)))
 }
 
 // Property-identifiers are defined as keywords using only uppercase letters.
 // Currently there are no more than two uppercase letters per identifier.
 
-#[derive(Debug)]
-enum Color {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Color {
     White,
     Black,
 }
 
+impl Color {
+    pub fn opponent(&self) -> Color {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::property::{Property, PropertyValue};
+    use crate::codec::{Algebraic, Go, CHESS};
+    use crate::date::PartialDate;
+    use crate::errors::ErrorKind;
+    use crate::game_result::{GameResult, WinReason};
+    use crate::lexer::TokenKind;
+    use crate::parser::{ParseOptions, Parser};
+    use crate::point::{BoardSize, Move, Point};
+    use crate::property::{Color, Property, PropertyType, PropertyValue};
+    use crate::rank::{Certainty, Rank, RankKind};
+    use crate::timing::Overtime;
     use test_case::test_case;
 
     #[test]
     fn can_parse_property() {
         let content = "FF[4]";
-        let property = Property::parse(content).unwrap().0;
+        let property = Property::parse(&mut Parser::new(content)).unwrap();
 
         assert_eq!(property.id, "FF");
 
         assert_eq!(property.values.len(), 1);
 
-        let val = property.values.get(0).unwrap();
+        let val = property.values.first().unwrap();
         assert_eq!(*val, PropertyValue::Number(4, 1, 4))
     }
 
@@ -253,22 +649,25 @@ mod tests {
     fn ff_property_validation(val: &str) {
         let content = format!("FF[{}]", val);
 
-        let property = Property::parse(content.as_str());
+        let property = Property::parse(&mut Parser::new(content.as_str()));
 
-        assert!(property.is_err());
+        let error = property.err().unwrap();
+        assert!(matches!(error.kind(), ErrorKind::InvalidValue(_)));
+        assert_eq!(error.property(), Some("FF"));
+        assert_eq!(error.position().unwrap().offset, 2);
     }
 
     #[test]
     fn ap_property_validation() {
         let content = "AP[Primiview:3.1]";
 
-        let property = Property::parse(content).unwrap().0;
+        let property = Property::parse(&mut Parser::new(content)).unwrap();
 
         assert_eq!(property.id, "AP");
 
         assert_eq!(property.values.len(), 1);
 
-        if let PropertyValue::Compose(x, y) = property.values.get(0).unwrap() {
+        if let PropertyValue::Compose(x, y) = property.values.first().unwrap() {
             if let PropertyValue::SimpleText(val) = &**x {
                 assert_eq!(val, "Primiview");
                 return;
@@ -284,18 +683,431 @@ mod tests {
 
     #[test]
     fn can_parse_property_multiple_value() {
-        let content = "FF[1][2][3][4]";
-        let property = Property::parse(content).unwrap().0;
+        let content = "MA[dh][di][dj][ej]";
+        let property = Property::parse(&mut Parser::new(content)).unwrap();
 
-        assert_eq!(property.id, "FF");
+        assert_eq!(property.id, "MA");
 
         assert_eq!(property.values.len(), 4);
 
-        for i in 0..4 {
-            assert_eq!(
-                *property.values.get(i).unwrap(),
-                PropertyValue::Number((i + 1) as u32, 1, 4)
+        assert_eq!(
+            property.values,
+            vec![
+                PropertyValue::Point(Point::new(3, 7)),
+                PropertyValue::Point(Point::new(3, 8)),
+                PropertyValue::Point(Point::new(3, 9)),
+                PropertyValue::Point(Point::new(4, 9)),
+            ]
+        );
+    }
+
+    #[test]
+    fn single_value_property_rejects_multiple_values() {
+        let content = "FF[1][2][3][4]";
+        let error = Property::parse(&mut Parser::new(content)).err().unwrap();
+
+        assert!(matches!(error.kind(), ErrorKind::InvalidValue(_)));
+        assert_eq!(error.property(), Some("FF"));
+    }
+
+    #[test_case("GM[1]", PropertyValue::Number(1, 1, u32::MAX) ; "Game")]
+    #[test_case("SZ[19]", PropertyValue::Number(19, 1, 52) ; "Square size")]
+    #[test_case("MN[112]", PropertyValue::Number(112, 0, u32::MAX) ; "Move number")]
+    #[test_case("KM[-59.5]", PropertyValue::Real(-59.5) ; "Komi")]
+    #[test_case("BL[87.00]", PropertyValue::Real(87.0) ; "Black time left")]
+    #[test_case("WL[300]", PropertyValue::Real(300.0) ; "White time left")]
+    #[test_case("GW[1]", PropertyValue::Double(false) ; "Good for white")]
+    #[test_case("GB[2]", PropertyValue::Double(true) ; "Very good for black")]
+    #[test_case("PL[B]", PropertyValue::Color(Color::Black) ; "Black to play")]
+    #[test_case("PL[W]", PropertyValue::Color(Color::White) ; "White to play")]
+    #[test_case("DO[]", PropertyValue::None ; "Doubtful move")]
+    #[test_case("N[Setup]", PropertyValue::SimpleText(String::from("Setup")) ; "Node name")]
+    #[test_case("RE[W+3.5]", PropertyValue::Result(GameResult::Win(Color::White, WinReason::Score(3.5))) ; "Result")]
+    #[test_case("RE[Unfinished]", PropertyValue::SimpleText(String::from("Unfinished")) ; "Unrecognised result")]
+    #[test_case("DT[1996-05-06,07]", PropertyValue::Date(vec![PartialDate::Day(1996, 5, 6), PartialDate::Day(1996, 5, 7)]) ; "Dates")]
+    #[test_case("OT[5x30 byo-yomi]", PropertyValue::Overtime(Overtime::ByoYomi { periods: 5, seconds: 30.0 }) ; "Overtime")]
+    #[test_case("WR[6d]", PropertyValue::Rank(Rank { kind: RankKind::Dan, number: 6, certainty: Certainty::Unmarked }) ; "Rank")]
+    #[test_case("BR[Honinbo]", PropertyValue::SimpleText(String::from("Honinbo")) ; "Unrecognised rank")]
+    #[test_case("C[Added two stones.]", PropertyValue::Text(String::from("Added two stones.")) ; "Comment")]
+    #[test_case("B[pd]", PropertyValue::Move(Move::Play(Point::new(15, 3))) ; "Black move")]
+    #[test_case("W[dp]", PropertyValue::Move(Move::Play(Point::new(3, 15))) ; "White move")]
+    #[test_case("W[]", PropertyValue::Move(Move::Pass) ; "White pass")]
+    #[test_case("B[tt]", PropertyValue::Move(Move::Pass) ; "FF3 black pass")]
+    fn can_parse_single_value(content: &str, expected: PropertyValue) {
+        let property = Property::parse(&mut Parser::new(content)).unwrap();
+
+        assert_eq!(property.values, vec![expected]);
+    }
+
+    #[test_case(r"C[erased in FF[3\] way]", PropertyValue::Text(String::from("erased in FF[3] way")) ; "Escaped bracket")]
+    #[test_case("C[o\\\nk\nnext]", PropertyValue::Text(String::from("ok\nnext")) ; "Soft and hard linebreaks")]
+    #[test_case("N[first\nsecond]", PropertyValue::SimpleText(String::from("first second")) ; "Simple text linebreak")]
+    #[test_case("GN[a\tb]", PropertyValue::SimpleText(String::from("a b")) ; "Simple text tab")]
+    fn can_decode_text_values(content: &str, expected: PropertyValue) {
+        let property = Property::parse(&mut Parser::new(content)).unwrap();
+
+        assert_eq!(property.values, vec![expected]);
+    }
+
+    #[test]
+    fn compose_splits_at_unescaped_colon() {
+        let property = Property::parse(&mut Parser::new(r"AP[My\:App:1\]0]")).unwrap();
+
+        assert_eq!(
+            property.values,
+            vec![PropertyValue::Compose(
+                Box::new(PropertyValue::SimpleText(String::from("My:App"))),
+                Box::new(PropertyValue::SimpleText(String::from("1]0")))
+            )]
+        );
+    }
+
+    #[test]
+    fn label_text_may_contain_escaped_colon() {
+        let property = Property::parse(&mut Parser::new(r"LB[dc:a\:b]")).unwrap();
+
+        assert_eq!(
+            property.values,
+            vec![PropertyValue::Compose(
+                Box::new(PropertyValue::Point(Point::new(3, 2))),
+                Box::new(PropertyValue::SimpleText(String::from("a:b")))
+            )]
+        );
+    }
+
+    #[test_case("FF[4]" ; "Number")]
+    #[test_case("KM[-59.5]" ; "Real")]
+    #[test_case("GB[2]" ; "Double")]
+    #[test_case("PL[W]" ; "Color")]
+    #[test_case("DO[]" ; "None")]
+    #[test_case("B[pd]" ; "Black move")]
+    #[test_case("W[]" ; "White pass")]
+    #[test_case("AB[dd][do:gq]" ; "Compressed stones")]
+    #[test_case("VW[]" ; "Empty elist")]
+    #[test_case("LB[dc:a\\:b]" ; "Escaped colon in label")]
+    #[test_case("AP[My\\:App:1.0]" ; "Escaped colon in application")]
+    #[test_case("C[FF[3\\] \\\\ way\nnext: line]" ; "Escaped text")]
+    #[test_case("ZZ[raw\\]value]" ; "Unknown")]
+    fn can_write_property(content: &str) {
+        let property = Property::parse(&mut Parser::new(content)).unwrap();
+
+        assert_eq!(property.to_sgf_with(&Go, BoardSize::default()), content);
+    }
+
+    #[test]
+    fn written_values_are_normalised() {
+        let property = Property::parse(&mut Parser::new("BL[87.00]")).unwrap();
+        assert_eq!(property.to_sgf_with(&Go, BoardSize::default()), "BL[87]");
+
+        let property = Property::parse(&mut Parser::new("B[tt]")).unwrap();
+        assert_eq!(property.to_sgf_with(&Go, BoardSize::default()), "B[]");
+
+        let property = Property::parse(&mut Parser::new("C[o\\\nk]")).unwrap();
+        assert_eq!(property.to_sgf_with(&Go, BoardSize::default()), "C[ok]");
+    }
+
+    #[test]
+    fn values_are_written_by_codec() {
+        let property = Property {
+            id: String::from("W"),
+            values: vec![PropertyValue::Move(Move::Step(
+                Point::new(4, 6),
+                Point::new(4, 4),
+            ))],
+        };
+        let square = BoardSize {
+            width: 8,
+            height: 8,
+        };
+
+        assert_eq!(
+            property.to_sgf_with(&Algebraic::new(CHESS, 8), square),
+            "W[e2e4]"
+        );
+        assert_eq!(property.to_sgf_with(&Go, square), "W[egee]");
+    }
+
+    #[test]
+    fn can_parse_composed_size() {
+        let property = Property::parse(&mut Parser::new("SZ[19:13]")).unwrap();
+
+        assert_eq!(
+            property.values,
+            vec![PropertyValue::Compose(
+                Box::new(PropertyValue::Number(19, 1, 52)),
+                Box::new(PropertyValue::Number(13, 1, 52))
+            )]
+        );
+    }
+
+    #[test]
+    fn can_parse_compressed_point_list() {
+        let property = Property::parse(&mut Parser::new("AB[dd][do:gq]")).unwrap();
+
+        assert_eq!(
+            property.values,
+            vec![
+                PropertyValue::Stone(Point::new(3, 3)),
+                PropertyValue::Compose(
+                    Box::new(PropertyValue::Stone(Point::new(3, 14))),
+                    Box::new(PropertyValue::Stone(Point::new(6, 16)))
+                )
+            ]
+        );
+    }
+
+    #[test]
+    fn can_expand_compressed_point_list() {
+        let property = Property::parse(&mut Parser::new("AE[ep][kn][pn:pq]")).unwrap();
+
+        assert!(property.is_point_list());
+        assert_eq!(
+            property.points(),
+            vec![
+                Point::new(4, 15),
+                Point::new(10, 13),
+                Point::new(15, 13),
+                Point::new(15, 14),
+                Point::new(15, 15),
+                Point::new(15, 16),
+            ]
+        );
+    }
+
+    #[test]
+    fn one_point_rectangle_is_error() {
+        let error = Property::parse(&mut Parser::new("AB[dd:dd]"))
+            .err()
+            .unwrap();
+
+        assert!(matches!(error.kind(), ErrorKind::InvalidValue(_)));
+    }
+
+    #[test]
+    fn composed_markup_is_not_a_point_list() {
+        let property = Property::parse(&mut Parser::new("AR[aa:sc]")).unwrap();
+
+        assert!(!property.is_point_list());
+        assert!(property.points().is_empty());
+    }
+
+    #[test]
+    fn can_build_compressed_point_list() {
+        let points = [
+            Point::new(3, 3),
+            Point::new(3, 14),
+            Point::new(4, 14),
+            Point::new(3, 15),
+            Point::new(4, 15),
+        ];
+        let property = Property::from_points("AB", &points).unwrap();
+
+        assert_eq!(
+            property.values,
+            vec![
+                PropertyValue::Stone(Point::new(3, 3)),
+                PropertyValue::Compose(
+                    Box::new(PropertyValue::Stone(Point::new(3, 14))),
+                    Box::new(PropertyValue::Stone(Point::new(4, 15)))
+                )
+            ]
+        );
+    }
+
+    #[test]
+    fn can_compress_point_list() {
+        let mut property = Property::parse(&mut Parser::new("TR[dd][de][df][ed][ee][ef]")).unwrap();
+        property.compress().unwrap();
+
+        assert_eq!(
+            property.values,
+            vec![PropertyValue::Compose(
+                Box::new(PropertyValue::Point(Point::new(3, 3))),
+                Box::new(PropertyValue::Point(Point::new(4, 5)))
+            )]
+        );
+    }
+
+    #[test]
+    fn from_points_rejects_other_properties() {
+        assert!(Property::from_points("B", &[Point::new(0, 0)]).is_err());
+        assert!(Property::from_points("AB", &[]).is_err());
+        assert!(Property::from_points("VW", &[]).unwrap().values.is_empty());
+    }
+
+    #[test]
+    fn can_parse_label() {
+        let property = Property::parse(&mut Parser::new("LB[dc:1][gs:ABCDEFGH]")).unwrap();
+
+        assert_eq!(
+            property.values[1],
+            PropertyValue::Compose(
+                Box::new(PropertyValue::Point(Point::new(6, 18))),
+                Box::new(PropertyValue::SimpleText(String::from("ABCDEFGH")))
             )
-        }
+        );
+    }
+
+    #[test]
+    fn can_parse_empty_elist() {
+        let property = Property::parse(&mut Parser::new("VW[]")).unwrap();
+
+        assert!(property.values.is_empty());
+    }
+
+    #[test_case("B[aa]", Some(PropertyType::Move) ; "Move property")]
+    #[test_case("BL[10]", Some(PropertyType::Move) ; "Timing")]
+    #[test_case("AE[aa]", Some(PropertyType::Setup) ; "Setup")]
+    #[test_case("SZ[19]", Some(PropertyType::Root) ; "Root")]
+    #[test_case("KM[6.5]", Some(PropertyType::GameInfo) ; "Game info")]
+    #[test_case("C[Comment]", None ; "No type")]
+    #[test_case("XX[private]", None ; "Unknown")]
+    fn properties_have_type(content: &str, expected: Option<PropertyType>) {
+        let property = Property::parse(&mut Parser::new(content)).unwrap();
+
+        assert_eq!(property.property_type(), expected);
+    }
+
+    #[test_case("PL[X]" ; "Invalid color")]
+    #[test_case("GB[3]" ; "Invalid double")]
+    #[test_case("KM[1e5]" ; "Exponent real")]
+    #[test_case("KM[.5]" ; "Missing whole part")]
+    #[test_case("DO[1]" ; "Value for none")]
+    #[test_case("AR[aa]" ; "Missing composed value")]
+    #[test_case("ST[4]" ; "Style out of range")]
+    #[test_case("TB[][aa]" ; "Empty value in list")]
+    #[test_case("AB[]" ; "Empty stone")]
+    #[test_case("B[zz]" ; "Move outside the board")]
+    #[test_case("AE[aa:zz]" ; "Compressed point outside the board")]
+    #[test_case("DT[1996-13-01]" ; "Month out of range")]
+    #[test_case("DT[2003-02-29]" ; "Day out of range")]
+    fn invalid_value_is_error(content: &str) {
+        let error = Property::parse(&mut Parser::new(content)).err().unwrap();
+
+        assert!(matches!(error.kind(), ErrorKind::InvalidValue(_)));
+    }
+
+    #[test_case("SZ[0]", "Value 0 not in range (min 1, max 52)" ; "Zero")]
+    #[test_case("SZ[60]", "Value 60 not in range (min 1, max 52)" ; "Too large")]
+    #[test_case("SZ[19:60]", "Value 60 not in range (min 1, max 52)" ; "Rectangular too large")]
+    fn board_size_out_of_range_is_error(content: &str, details: &str) {
+        let error = Property::parse(&mut Parser::new(content)).err().unwrap();
+
+        assert_eq!(
+            *error.kind(),
+            ErrorKind::InvalidValue(String::from(details))
+        );
+        assert_eq!(error.property(), Some("SZ"));
+    }
+
+    #[test_case("DT[2004.03.13]" ; "Dots")]
+    #[test_case("DT[2004-03-13 (Sat)]" ; "Weekday")]
+    #[test_case("DT[1996/05/06]" ; "Slashes")]
+    fn non_iso_date_is_text_with_warning(content: &str) {
+        let mut parser = Parser::new(content);
+        let property = Property::parse(&mut parser).unwrap();
+        let text = &content[3..content.len() - 1];
+
+        assert_eq!(
+            property.values,
+            vec![PropertyValue::SimpleText(String::from(text))]
+        );
+
+        let warnings = parser.take_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(matches!(warnings[0].kind(), ErrorKind::InvalidValue(_)));
+        assert_eq!(warnings[0].property(), Some("DT"));
+    }
+
+    #[test_case("RE[Unfinished]", true ; "Unrecognised")]
+    #[test_case("RE[b+res]", true ; "Non-standard in strict mode")]
+    #[test_case("RE[Unfinished]", false ; "Unrecognised in lenient mode")]
+    fn unrecognised_result_is_text_with_warning(content: &str, strict: bool) {
+        let options = ParseOptions {
+            strict,
+            ..ParseOptions::default()
+        };
+        let mut parser = Parser::with_options(content, options);
+        let property = Property::parse(&mut parser).unwrap();
+        let text = &content[3..content.len() - 1];
+
+        assert_eq!(
+            property.values,
+            vec![PropertyValue::SimpleText(String::from(text))]
+        );
+
+        let warnings = parser.take_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(matches!(warnings[0].kind(), ErrorKind::InvalidValue(_)));
+        assert_eq!(warnings[0].property(), Some("RE"));
+    }
+
+    #[test_case("RE[b+res]" ; "Lowercase resignation")]
+    #[test_case("RE[Black wins by resignation]" ; "Words")]
+    fn non_standard_result_is_read_in_lenient_mode(content: &str) {
+        let options = ParseOptions {
+            strict: false,
+            ..ParseOptions::default()
+        };
+        let mut parser = Parser::with_options(content, options);
+        let property = Property::parse(&mut parser).unwrap();
+
+        assert_eq!(
+            property.values,
+            vec![PropertyValue::Result(GameResult::Win(
+                Color::Black,
+                WinReason::Resignation
+            ))]
+        );
+        assert!(parser.take_warnings().is_empty());
+    }
+
+    #[test]
+    fn unknown_property_is_kept_with_warning() {
+        let mut parser = Parser::new(r"MULTIGOGM[1][a\]b]");
+        let property = Property::parse(&mut parser).unwrap();
+
+        assert_eq!(property.id, "MULTIGOGM");
+        assert_eq!(
+            property.values,
+            vec![
+                PropertyValue::Unknown(String::from("1")),
+                PropertyValue::Unknown(String::from(r"a\]b"))
+            ]
+        );
+
+        let warnings = parser.take_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(*warnings[0].kind(), ErrorKind::UnknownProperty);
+        assert_eq!(warnings[0].property(), Some("MULTIGOGM"));
+        assert_eq!(warnings[0].position().unwrap().offset, 0);
+    }
+
+    #[test]
+    fn can_parse_multi_byte_value() {
+        let content = "AP[棋譜:1.0]";
+        let property = Property::parse(&mut Parser::new(content)).unwrap();
+
+        assert_eq!(
+            *property.values.first().unwrap(),
+            PropertyValue::Compose(
+                Box::new(PropertyValue::SimpleText(String::from("棋譜"))),
+                Box::new(PropertyValue::SimpleText(String::from("1.0")))
+            )
+        );
+    }
+
+    #[test]
+    fn property_without_value_is_error() {
+        let error = Property::parse(&mut Parser::new("FF;")).err().unwrap();
+
+        assert_eq!(
+            *error.kind(),
+            ErrorKind::UnexpectedToken {
+                expected: TokenKind::PropValue,
+                found: TokenKind::NodeStart
+            }
+        );
+        assert_eq!(error.property(), Some("FF"));
     }
 }
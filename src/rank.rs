@@ -0,0 +1,172 @@
+// From the SGF spec (https://www.red-bean.com/sgf/properties.html#BR):
+// BR and WR give the ranks of the players. The following format should be used: "..k" or "..kyu"
+// for kyu ranks and "..d" or "..dan" for dan ranks. Go servers may want to add '?' for an
+// uncertain rating and '*' for an established rating.
+
+// Professional ranks are commonly written as "..p" and rank above all amateur ranks.
+
+use crate::errors::{ErrorKind, SgfParseError};
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum RankKind {
+    Kyu,
+    Dan,
+    Pro,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Certainty {
+    #[default]
+    Unmarked,
+    // Marked with '?'.
+    Uncertain,
+    // Marked with '*'.
+    Established,
+}
+
+// Ranks are ordered weakest first: 30k < 1k < 1d < 9d < 1p. Equal ranks are ordered by certainty.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rank {
+    pub kind: RankKind,
+    pub number: u8,
+    pub certainty: Certainty,
+}
+
+impl Rank {
+    pub fn parse(val: &str) -> Result<Self, SgfParseError> {
+        let lower = val.trim().to_ascii_lowercase();
+
+        let (rest, certainty) = match lower.strip_suffix('?') {
+            Some(rest) => (rest, Certainty::Uncertain),
+            None => match lower.strip_suffix('*') {
+                Some(rest) => (rest, Certainty::Established),
+                None => (lower.as_str(), Certainty::Unmarked),
+            },
+        };
+
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let (number, kind) = rest.split_at(digits);
+
+        let kind = match kind.trim_start() {
+            "k" | "kyu" => RankKind::Kyu,
+            "d" | "dan" => RankKind::Dan,
+            "p" | "pro" => RankKind::Pro,
+            _ => return Err(invalid_rank(val)),
+        };
+        let number: u8 = number.parse().map_err(|_| invalid_rank(val))?;
+
+        let max = match kind {
+            RankKind::Kyu => 50,
+            RankKind::Dan | RankKind::Pro => 9,
+        };
+        if number == 0 || number > max {
+            return Err(invalid_rank(val));
+        }
+
+        Ok(Rank {
+            kind,
+            number,
+            certainty,
+        })
+    }
+
+    // The short form of the rank, e.g. "5d" or "2k?".
+    pub fn to_sgf(&self) -> String {
+        let kind = match self.kind {
+            RankKind::Kyu => 'k',
+            RankKind::Dan => 'd',
+            RankKind::Pro => 'p',
+        };
+        let marker = match self.certainty {
+            Certainty::Unmarked => "",
+            Certainty::Uncertain => "?",
+            Certainty::Established => "*",
+        };
+
+        format!("{}{}{}", self.number, kind, marker)
+    }
+
+    // Higher is stronger; kyu ranks count down towards 1k.
+    fn strength(&self) -> i32 {
+        match self.kind {
+            RankKind::Kyu => -(self.number as i32),
+            RankKind::Dan => self.number as i32,
+            RankKind::Pro => 100 + self.number as i32,
+        }
+    }
+}
+
+impl Ord for Rank {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.strength()
+            .cmp(&other.strength())
+            .then(self.certainty.cmp(&other.certainty))
+    }
+}
+
+impl PartialOrd for Rank {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+fn invalid_rank(val: &str) -> SgfParseError {
+    SgfParseError::new(ErrorKind::InvalidValue(format!("'{}' is not a rank", val)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Certainty, Rank, RankKind};
+    use test_case::test_case;
+
+    #[test_case("5d", RankKind::Dan, 5, Certainty::Unmarked ; "Dan")]
+    #[test_case("3k", RankKind::Kyu, 3, Certainty::Unmarked ; "Kyu")]
+    #[test_case("9p", RankKind::Pro, 9, Certainty::Unmarked ; "Pro")]
+    #[test_case("1d*", RankKind::Dan, 1, Certainty::Established ; "Established")]
+    #[test_case("2k?", RankKind::Kyu, 2, Certainty::Uncertain ; "Uncertain")]
+    #[test_case("12 kyu", RankKind::Kyu, 12, Certainty::Unmarked ; "Long form")]
+    #[test_case("4Dan", RankKind::Dan, 4, Certainty::Unmarked ; "Capitalised")]
+    fn can_parse_rank(val: &str, kind: RankKind, number: u8, certainty: Certainty) {
+        assert_eq!(
+            Rank::parse(val).unwrap(),
+            Rank {
+                kind,
+                number,
+                certainty
+            }
+        );
+    }
+
+    #[test_case("" ; "Empty")]
+    #[test_case("d" ; "Missing number")]
+    #[test_case("5" ; "Missing kind")]
+    #[test_case("0k" ; "Zero")]
+    #[test_case("10d" ; "Dan out of range")]
+    #[test_case("5x" ; "Unknown kind")]
+    #[test_case("Meijin" ; "Title")]
+    fn invalid_rank_is_error(val: &str) {
+        assert!(Rank::parse(val).is_err());
+    }
+
+    #[test_case("5 dan", "5d" ; "Dan")]
+    #[test_case("2kyu?", "2k?" ; "Uncertain kyu")]
+    #[test_case("9p*", "9p*" ; "Established pro")]
+    fn writes_short_form(val: &str, expected: &str) {
+        assert_eq!(Rank::parse(val).unwrap().to_sgf(), expected);
+    }
+
+    #[test]
+    fn ranks_are_ordered_by_strength() {
+        let mut ranks: Vec<Rank> = ["1p", "1k", "9d", "30k", "1d", "5k"]
+            .iter()
+            .map(|val| Rank::parse(val).unwrap())
+            .collect();
+        ranks.sort();
+
+        let sorted: Vec<String> = ranks.iter().map(Rank::to_sgf).collect();
+        assert_eq!(sorted, vec!["30k", "5k", "1k", "1d", "9d", "1p"]);
+    }
+}
@@ -0,0 +1,418 @@
+// From the Go section of the SGF spec (https://www.red-bean.com/sgf/go.html#RU):
+// RU gives the rules used for the game. Names are e.g. "AGA", "GOE" (Ing), "Japanese" and "NZ".
+
+// The rulesets differ in how repeated positions are prevented and whether suicide is allowed:
+// - Simple ko: a move may not recreate the position before the opponent's last move.
+// - Positional superko: a move may not recreate any earlier position.
+// - Situational superko: a move may not recreate an earlier position with the same player to move.
+
+// KO forces a move to be executed even if it's illegal, so moves in nodes with KO aren't checked.
+// A stone outside the board is still reported, as it can't be played at all.
+
+use crate::board::{Board, BoardState};
+use crate::codec;
+use crate::game_tree::GameTree;
+use crate::navigation::Location;
+use crate::node::Node;
+use crate::point::{Move, Point};
+use crate::property::{Color, PropertyValue};
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ruleset {
+    Japanese,
+    Chinese,
+    Aga,
+    NewZealand,
+    TrompTaylor,
+    Ing,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KoRule {
+    Simple,
+    PositionalSuperko,
+    SituationalSuperko,
+}
+
+// Area scoring counts stones plus surrounded points, territory scoring surrounded points plus
+// prisoners.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScoringMethod {
+    Area,
+    Territory,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    // The point already holds a stone, so the move was not played.
+    Occupied,
+    // A move or setup stone outside the board, which was not played.
+    OutsideBoard,
+    Suicide,
+    Ko,
+    Superko,
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::Occupied => write!(f, "Move is played on an occupied point"),
+            Violation::OutsideBoard => write!(f, "Stone is placed outside the board"),
+            Violation::Suicide => write!(f, "Suicide is not allowed"),
+            Violation::Ko => write!(f, "Move retakes a ko immediately"),
+            Violation::Superko => write!(f, "Move repeats an earlier position"),
+        }
+    }
+}
+
+// An illegal move, found at the node with the given path (see `GameTree::node_at`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub path: Vec<usize>,
+    pub violation: Violation,
+}
+
+impl Ruleset {
+    pub fn parse(val: &str) -> Option<Self> {
+        let ruleset = match val.trim().to_ascii_lowercase().as_str() {
+            "japanese" | "jp" => Ruleset::Japanese,
+            "chinese" | "cn" => Ruleset::Chinese,
+            "aga" => Ruleset::Aga,
+            "nz" | "new zealand" => Ruleset::NewZealand,
+            "tromp-taylor" | "tromp taylor" | "tt" => Ruleset::TrompTaylor,
+            "goe" | "ing" => Ruleset::Ing,
+            _ => return None,
+        };
+
+        Some(ruleset)
+    }
+
+    // The ruleset given by RU in the root node; Japanese if it's missing or not recognised.
+    pub fn of(tree: &GameTree) -> Self {
+        match tree.root().get("RU").and_then(|prop| prop.values.first()) {
+            Some(PropertyValue::SimpleText(val)) => {
+                Ruleset::parse(val).unwrap_or(Ruleset::Japanese)
+            }
+            _ => Ruleset::Japanese,
+        }
+    }
+
+    pub fn ko_rule(&self) -> KoRule {
+        match self {
+            Ruleset::Japanese => KoRule::Simple,
+            Ruleset::Chinese | Ruleset::TrompTaylor => KoRule::PositionalSuperko,
+            // Ing's ko rules are approximated by situational superko.
+            Ruleset::Aga | Ruleset::NewZealand | Ruleset::Ing => KoRule::SituationalSuperko,
+        }
+    }
+
+    pub fn scoring_method(&self) -> ScoringMethod {
+        match self {
+            Ruleset::Japanese => ScoringMethod::Territory,
+            _ => ScoringMethod::Area,
+        }
+    }
+
+    // Points given to White for a handicap game. Under area scoring each handicap stone would
+    // otherwise count for Black; AGA compensates for all but the first one.
+    pub fn handicap_compensation(&self, handicap: u32) -> u32 {
+        match self {
+            Ruleset::Chinese | Ruleset::Ing => handicap,
+            Ruleset::Aga => handicap.saturating_sub(1),
+            _ => 0,
+        }
+    }
+
+    pub fn allows_suicide(&self) -> bool {
+        matches!(
+            self,
+            Ruleset::NewZealand | Ruleset::TrompTaylor | Ruleset::Ing
+        )
+    }
+}
+
+// Checks every move of every variation under the ruleset given by RU.
+pub fn check(tree: &GameTree) -> Vec<Diagnostic> {
+    check_with(tree, Ruleset::of(tree))
+}
+
+// Only Go games are checked; other games have no diagnostics.
+pub fn check_with(tree: &GameTree, ruleset: Ruleset) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    if tree.game() != codec::GO {
+        return diagnostics;
+    }
+    // The positions along the variation being checked, i.e. after each node.
+    let mut history: Vec<Position> = vec![];
+    let mut pending = vec![(Location::root(tree), vec![], BoardState::start(tree), 0)];
+
+    while let Some((location, path, mut state, depth)) = pending.pop() {
+        history.truncate(depth);
+
+        let node = location.node();
+        let played = played(node);
+        let forced = node.get("KO").is_some();
+
+        let violation = match state.apply(node) {
+            Err(_) => match unplayable(&state.board, node) {
+                Violation::Occupied if forced => None,
+                violation => Some(violation),
+            },
+            Ok(()) if forced => None,
+            Ok(()) => match played {
+                Some(Move::Play(point)) => judge(ruleset, &state, &history, point),
+                _ => None,
+            },
+        };
+
+        if let Some(violation) = violation {
+            diagnostics.push(Diagnostic {
+                path: path.clone(),
+                violation,
+            });
+        }
+
+        history.push(Position {
+            board: state.board.clone(),
+            to_move: state.to_move,
+            moved: played.is_some(),
+        });
+
+        // Pushed in reverse, so variations are checked in file order.
+        let children = location.children();
+        for (index, child) in children.into_iter().enumerate().rev() {
+            let mut child_path = path.clone();
+            child_path.push(index);
+            pending.push((child, child_path, state.clone(), depth + 1));
+        }
+    }
+
+    diagnostics
+}
+
+// The position after a node.
+struct Position {
+    board: Board,
+    to_move: Color,
+    // Whether the node had a move, including a pass.
+    moved: bool,
+}
+
+fn played(node: &Node) -> Option<Move> {
+    ["B", "W"].iter().find_map(
+        |id| match node.get(id).and_then(|prop| prop.values.first()) {
+            Some(PropertyValue::Move(played)) => Some(played.clone()),
+            _ => None,
+        },
+    )
+}
+
+// Why a node couldn't be applied: a stone outside the board, or else a move on an occupied point.
+fn unplayable(board: &Board, node: &Node) -> Violation {
+    let size = board.size();
+    let setup = ["AE", "AB", "AW"]
+        .iter()
+        .filter_map(|id| node.get(id))
+        .flat_map(|prop| prop.points());
+    let moves = ["B", "W"].iter().filter_map(|id| {
+        match node.get(id).and_then(|prop| prop.values.first()) {
+            Some(PropertyValue::Move(Move::Play(point))) => Some(*point),
+            _ => None,
+        }
+    });
+
+    let mut points = setup.chain(moves);
+    if points.any(|point| !size.contains(point)) {
+        Violation::OutsideBoard
+    } else {
+        Violation::Occupied
+    }
+}
+
+// Judges a move given the state after it was played and the positions before it.
+fn judge(
+    ruleset: Ruleset,
+    state: &BoardState,
+    history: &[Position],
+    point: Point,
+) -> Option<Violation> {
+    if state.board.get(point).is_none() && !ruleset.allows_suicide() {
+        return Some(Violation::Suicide);
+    }
+
+    let repeated = match ruleset.ko_rule() {
+        KoRule::Simple => {
+            // The position before the opponent's last move; nodes without a move don't count.
+            let before_opponent = history
+                .iter()
+                .rposition(|position| position.moved)
+                .and_then(|index| index.checked_sub(1))
+                .map(|index| &history[index]);
+            return match before_opponent {
+                Some(position) if position.board == state.board => Some(Violation::Ko),
+                _ => None,
+            };
+        }
+        KoRule::PositionalSuperko => history.iter().any(|position| position.board == state.board),
+        KoRule::SituationalSuperko => history
+            .iter()
+            .any(|position| position.board == state.board && position.to_move == state.to_move),
+    };
+
+    if repeated {
+        Some(Violation::Superko)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, check_with, Diagnostic, KoRule, Ruleset, Violation};
+    use crate::point::{Move, Point};
+    use crate::property::PropertyValue;
+    use test_case::test_case;
+
+    // A ko at the top: Black takes at cb, White retakes at bb.
+    const KO: &str = "(;SZ[5]AB[ba][ab][bc]AW[ca][bb][db][cc];B[cb];W[bb])";
+
+    fn check_str(source: &str) -> Vec<Diagnostic> {
+        let collection = crate::parse_str(source).unwrap();
+        check(&collection.game_trees()[0])
+    }
+
+    #[test_case("Japanese", Ruleset::Japanese ; "Japanese")]
+    #[test_case("AGA", Ruleset::Aga ; "Aga")]
+    #[test_case("NZ", Ruleset::NewZealand ; "New Zealand")]
+    #[test_case("GOE", Ruleset::Ing ; "Goe")]
+    #[test_case(" chinese ", Ruleset::Chinese ; "Lower case with spaces")]
+    #[test_case("Tromp-Taylor", Ruleset::TrompTaylor ; "Tromp Taylor")]
+    fn can_parse_ruleset(val: &str, expected: Ruleset) {
+        assert_eq!(Ruleset::parse(val), Some(expected));
+    }
+
+    #[test_case(Ruleset::Japanese, KoRule::Simple, false ; "Japanese")]
+    #[test_case(Ruleset::Chinese, KoRule::PositionalSuperko, false ; "Chinese")]
+    #[test_case(Ruleset::Aga, KoRule::SituationalSuperko, false ; "Aga")]
+    #[test_case(Ruleset::NewZealand, KoRule::SituationalSuperko, true ; "New Zealand")]
+    #[test_case(Ruleset::TrompTaylor, KoRule::PositionalSuperko, true ; "Tromp Taylor")]
+    fn rulesets_differ(ruleset: Ruleset, ko_rule: KoRule, suicide: bool) {
+        assert_eq!(ruleset.ko_rule(), ko_rule);
+        assert_eq!(ruleset.allows_suicide(), suicide);
+    }
+
+    #[test]
+    fn example_has_no_illegal_moves_but_suicide() {
+        let collection = crate::parse_file("resources/example.sgf").unwrap();
+        let tree = &collection.game_trees()[0];
+
+        let mut path = vec![4];
+        path.extend([0; 20]);
+        assert_eq!(
+            check(tree),
+            vec![Diagnostic {
+                path: path.clone(),
+                violation: Violation::Suicide
+            }]
+        );
+        assert!(check_with(tree, Ruleset::NewZealand).is_empty());
+    }
+
+    #[test]
+    fn immediate_ko_retake_is_illegal() {
+        assert_eq!(
+            check_str(KO),
+            vec![Diagnostic {
+                path: vec![0, 0],
+                violation: Violation::Ko
+            }]
+        );
+    }
+
+    #[test]
+    fn superko_rules_report_ko_as_superko() {
+        let source = KO.replace("SZ[5]", "SZ[5]RU[Chinese]");
+
+        assert_eq!(check_str(&source)[0].violation, Violation::Superko);
+    }
+
+    #[test]
+    fn ko_property_forces_move() {
+        let source = KO.replace("W[bb]", "W[bb]KO[]");
+
+        assert!(check_str(&source).is_empty());
+    }
+
+    #[test]
+    fn retake_after_passes_is_only_illegal_under_superko() {
+        let source = KO.replace(";W[bb]", ";W[];B[];W[bb]");
+        assert!(check_str(&source).is_empty());
+
+        let source = source.replace("SZ[5]", "SZ[5]RU[Chinese]");
+        assert_eq!(
+            check_str(&source),
+            vec![Diagnostic {
+                path: vec![0, 0, 0, 0],
+                violation: Violation::Superko
+            }]
+        );
+    }
+
+    #[test]
+    fn occupied_point_is_reported() {
+        assert_eq!(
+            check_str("(;SZ[9];B[ee];W[ee];W[ff])"),
+            vec![Diagnostic {
+                path: vec![0, 0],
+                violation: Violation::Occupied
+            }]
+        );
+    }
+
+    #[test]
+    fn ko_retake_after_node_without_move_is_illegal() {
+        let source = KO.replace(";W[bb]", ";C[thinking];W[bb]");
+
+        assert_eq!(
+            check_str(&source),
+            vec![Diagnostic {
+                path: vec![0, 0, 0],
+                violation: Violation::Ko
+            }]
+        );
+    }
+
+    #[test]
+    fn stone_outside_board_is_reported() {
+        let collection = crate::parse_str("(;SZ[9];B[ee];W[ff])").unwrap();
+        let mut tree = collection.game_trees()[0].clone();
+        tree.nodes_mut()[2].properties[0].values =
+            vec![PropertyValue::Move(Move::Play(Point::new(12, 0)))];
+
+        assert_eq!(
+            check(&tree),
+            vec![Diagnostic {
+                path: vec![0, 0],
+                violation: Violation::OutsideBoard
+            }]
+        );
+    }
+
+    #[test]
+    fn ko_property_forces_move_on_occupied_point() {
+        assert!(check_str("(;SZ[9];B[ee];W[ee]KO[])").is_empty());
+        assert_eq!(
+            check_str("(;SZ[9];B[ee];W[ee])")[0].violation,
+            Violation::Occupied
+        );
+    }
+
+    #[test]
+    fn checks_every_variation() {
+        let diagnostics = check_str("(;SZ[9];B[ee](;W[ee])(;W[dd])(;W[ee]))");
+
+        let paths: Vec<Vec<usize>> = diagnostics.into_iter().map(|d| d.path).collect();
+        assert_eq!(paths, vec![vec![0, 0], vec![0, 2]]);
+    }
+}
@@ -0,0 +1,259 @@
+// Scores the final position of a Go game record.
+
+// From the Go section of the SGF spec (https://www.red-bean.com/sgf/go.html):
+// TB and TW specify the points of Black's and White's territory. Stones of the other color inside
+// a territory are dead. Without TB/TW, stones marked with MA in the final node are taken as dead
+// and territory is every empty region bordered by one color only.
+
+// KM is added to White's score. Under area scoring, White also gets the handicap compensation of
+// the ruleset given by RU.
+
+use crate::board::{replay, Board};
+use crate::errors::SgfParseError;
+use crate::game_result::GameResult;
+use crate::game_tree::GameTree;
+use crate::point::Point;
+use crate::property::{Color, PropertyValue};
+use crate::rules::{Ruleset, ScoringMethod};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Score {
+    pub black: f64,
+    pub white: f64,
+}
+
+impl Score {
+    // Positive if Black wins.
+    pub fn margin(&self) -> f64 {
+        self.black - self.white
+    }
+
+    pub fn winner(&self) -> Option<Color> {
+        match self.margin() {
+            margin if margin > 0.0 => Some(Color::Black),
+            margin if margin < 0.0 => Some(Color::White),
+            _ => None,
+        }
+    }
+}
+
+// A record whose RE property disagrees with the score of its final position.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultMismatch {
+    pub stated: GameResult,
+    pub computed: Score,
+}
+
+// Scores the position after the node at `path` (see `GameTree::node_at`).
+pub fn score(tree: &GameTree, path: &[usize], ruleset: Ruleset) -> Result<Score, SgfParseError> {
+    let state = replay(tree, path)?.pop().expect("replay includes the root");
+    let last = tree.node_at(path).unwrap_or_else(|| tree.root());
+
+    let mut board = state.board;
+    let mut prisoners = state.prisoners;
+
+    let (black_territory, white_territory) = match (last.get("TB"), last.get("TW")) {
+        (None, None) => {
+            let dead = last.get("MA").map(|prop| prop.points()).unwrap_or_default();
+            for point in dead {
+                match board.get(point) {
+                    Some(Color::Black) => prisoners.white += 1,
+                    Some(Color::White) => prisoners.black += 1,
+                    None => continue,
+                }
+                board.set(point, None)?;
+            }
+
+            territories(&board)
+        }
+        (black, white) => {
+            let black = black.map(|prop| prop.points()).unwrap_or_default();
+            let white = white.map(|prop| prop.points()).unwrap_or_default();
+
+            for (territory, owner) in [(&black, Color::Black), (&white, Color::White)] {
+                for point in territory {
+                    if board.get(*point) == Some(owner.opponent()) {
+                        board.set(*point, None)?;
+                        match owner {
+                            Color::Black => prisoners.black += 1,
+                            Color::White => prisoners.white += 1,
+                        }
+                    }
+                }
+            }
+
+            (black.len(), white.len())
+        }
+    };
+
+    let mut score = match ruleset.scoring_method() {
+        ScoringMethod::Area => Score {
+            black: (board.stones(Color::Black).len() + black_territory) as f64,
+            white: (board.stones(Color::White).len() + white_territory) as f64,
+        },
+        ScoringMethod::Territory => Score {
+            black: (black_territory as u32 + prisoners.black) as f64,
+            white: (white_territory as u32 + prisoners.white) as f64,
+        },
+    };
+
+    if let Some(PropertyValue::Real(komi)) = game_info(tree, path, "KM") {
+        score.white += komi;
+    }
+    if ruleset.scoring_method() == ScoringMethod::Area {
+        if let Some(PropertyValue::Number(handicap, _, _)) = game_info(tree, path, "HA") {
+            score.white += ruleset.handicap_compensation(*handicap) as f64;
+        }
+    }
+
+    Ok(score)
+}
+
+// Compares the score of the position after the node at `path` with RE, under the ruleset given
+// by RU. Results that don't state a score, e.g. a resignation, are not compared.
+pub fn verify_result(
+    tree: &GameTree,
+    path: &[usize],
+) -> Result<Option<ResultMismatch>, SgfParseError> {
+    let stated = match game_info(tree, path, "RE") {
+        Some(PropertyValue::Result(stated)) => *stated,
+        _ => return Ok(None),
+    };
+
+    let expected = match stated.margin() {
+        Some(margin) => margin,
+        None => return Ok(None),
+    };
+
+    let computed = score(tree, path, Ruleset::of(tree))?;
+    if (computed.margin() - expected).abs() < f64::EPSILON {
+        return Ok(None);
+    }
+
+    Ok(Some(ResultMismatch { stated, computed }))
+}
+
+fn game_info<'a>(tree: &'a GameTree, path: &[usize], id: &str) -> Option<&'a PropertyValue> {
+    tree.game_info(path, id)
+        .and_then(|prop| prop.values.first())
+}
+
+// Counts the empty points surrounded by only black or only white stones.
+fn territories(board: &Board) -> (usize, usize) {
+    let mut seen = vec![];
+    let (mut black, mut white) = (0, 0);
+
+    for start in board.points() {
+        if board.get(start).is_some() || seen.contains(&start) {
+            continue;
+        }
+
+        let mut region: Vec<Point> = vec![start];
+        let mut borders = (false, false);
+        let mut index = 0;
+        while index < region.len() {
+            for neighbour in board.neighbours(region[index]) {
+                match board.get(neighbour) {
+                    Some(Color::Black) => borders.0 = true,
+                    Some(Color::White) => borders.1 = true,
+                    None if !region.contains(&neighbour) => region.push(neighbour),
+                    None => (),
+                }
+            }
+            index += 1;
+        }
+
+        match borders {
+            (true, false) => black += region.len(),
+            (false, true) => white += region.len(),
+            _ => (),
+        }
+        seen.extend(region);
+    }
+
+    (black, white)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{score, verify_result, Score};
+    use crate::rules::Ruleset;
+    use test_case::test_case;
+
+    // Black owns columns a-b, White column e; a white stone at aa is dead.
+    const GAME: &str = "(;SZ[5]KM[0.5]AB[ca][cb][cc][cd][ce]AW[da][db][dc][dd][de];W[aa]";
+
+    fn tree(source: &str) -> crate::GameTree {
+        crate::parse_str(source).unwrap().game_trees()[0].clone()
+    }
+
+    #[test_case(Ruleset::Chinese, 15.0, 10.5 ; "Area")]
+    #[test_case(Ruleset::Japanese, 11.0, 5.5 ; "Territory")]
+    fn can_score_with_territory_markup(ruleset: Ruleset, black: f64, white: f64) {
+        let tree = tree(&format!("{};TB[aa:be]TW[ea:ee])", GAME));
+
+        assert_eq!(
+            score(&tree, &[0, 0], ruleset).unwrap(),
+            Score { black, white }
+        );
+    }
+
+    #[test_case(Ruleset::Chinese, 15.0, 10.5 ; "Area")]
+    #[test_case(Ruleset::Japanese, 11.0, 5.5 ; "Territory")]
+    fn can_score_with_dead_stones(ruleset: Ruleset, black: f64, white: f64) {
+        let tree = tree(&format!("{};MA[aa])", GAME));
+
+        assert_eq!(
+            score(&tree, &[0, 0], ruleset).unwrap(),
+            Score { black, white }
+        );
+    }
+
+    #[test]
+    fn unremoved_dead_stone_spoils_territory() {
+        let tree = tree(&format!("{})", GAME));
+
+        let score = score(&tree, &[0], Ruleset::Chinese).unwrap();
+        assert_eq!(score.black, 5.0);
+    }
+
+    #[test]
+    fn handicap_compensation_depends_on_ruleset() {
+        let tree = tree("(;SZ[5]HA[2]KM[0.5]AB[bb][dd])");
+
+        let chinese = score(&tree, &[], Ruleset::Chinese).unwrap();
+        let aga = score(&tree, &[], Ruleset::Aga).unwrap();
+        let japanese = score(&tree, &[], Ruleset::Japanese).unwrap();
+
+        assert_eq!(chinese.white, 2.5);
+        assert_eq!(aga.white, 1.5);
+        assert_eq!(japanese.white, 0.5);
+    }
+
+    #[test_case("B+5.5", false ; "Matching")]
+    #[test_case("W+2.5", true ; "Wrong winner")]
+    #[test_case("B+1.5", true ; "Wrong margin")]
+    #[test_case("B+R", false ; "Resignation")]
+    fn can_verify_result(result: &str, mismatch: bool) {
+        let source = GAME.replace("KM[0.5]", &format!("KM[0.5]RE[{}]", result));
+        let tree = tree(&format!("{};TB[aa:be]TW[ea:ee])", source));
+
+        let verified = verify_result(&tree, &[0, 0]).unwrap();
+        assert_eq!(verified.is_some(), mismatch);
+        if let Some(verified) = verified {
+            assert_eq!(verified.stated.to_sgf(), result);
+            assert_eq!(verified.computed.margin(), 5.5);
+        }
+    }
+
+    #[test]
+    fn finds_result_in_game_info_node() {
+        let collection = crate::parse_file("resources/example.sgf").unwrap();
+        let tree = &collection.game_trees()[1];
+
+        // RE[W+3.5] after two moves and no territory: the board is a draw.
+        let verified = verify_result(tree, &[0, 0]).unwrap().unwrap();
+        assert_eq!(verified.stated.to_sgf(), "W+3.5");
+        assert_eq!(verified.computed.margin(), 0.0);
+    }
+}
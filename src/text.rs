@@ -0,0 +1,152 @@
+// From the SGF spec (https://www.red-bean.com/sgf/sgf4.html#text):
+
+// Text: White spaces other than linebreaks are converted to space (e.g. no tab, vertical tab, ..).
+// SimpleText: Whitespaces other than space must be converted to space, i.e. there's no newline!
+
+// Soft linebreaks (linebreaks preceded by "\") are removed in both. Linebreaks are coded
+// differently on different systems, so "\n", "\r\n", "\n\r" and "\r" all count as one linebreak.
+
+// "\" is the escape character. Any char following "\" is inserted verbatim
+// (exception: whitespaces still have to be converted to space!).
+// Following chars have to be escaped: "]", "\" and ":" (only if used in compose data type).
+
+use crate::chars;
+
+// Splits a composed value at the first ':' that isn't escaped.
+pub fn split_compose(raw: &str) -> Option<(&str, &str)> {
+    let mut escaped = false;
+
+    for (index, character) in raw.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+
+        match character {
+            chars::ESCAPE => escaped = true,
+            chars::COMPOSE_SEPARATOR => {
+                return Some((&raw[..index], &raw[index + character.len_utf8()..]));
+            }
+            _ => (),
+        }
+    }
+
+    None
+}
+
+pub fn decode_text(raw: &str) -> String {
+    decode(raw, "\n")
+}
+
+pub fn decode_simple_text(raw: &str) -> String {
+    decode(raw, " ")
+}
+
+// Escapes a decoded text so it can be written as a property value. `in_compose` also escapes ':',
+// which would otherwise split a composed value.
+pub fn escape(text: &str, in_compose: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for character in text.chars() {
+        let needs_escape = match character {
+            chars::PROP_VAL_END | chars::ESCAPE => true,
+            chars::COMPOSE_SEPARATOR => in_compose,
+            _ => false,
+        };
+
+        if needs_escape {
+            escaped.push(chars::ESCAPE);
+        }
+        escaped.push(character);
+    }
+
+    escaped
+}
+
+fn decode(raw: &str, hard_linebreak: &str) -> String {
+    let mut decoded = String::with_capacity(raw.len());
+    let mut characters = raw.chars().peekable();
+
+    while let Some(character) = characters.next() {
+        let escaped = character == chars::ESCAPE;
+        let character = match (escaped, characters.peek()) {
+            (true, Some(_)) => characters.next().unwrap(),
+            // A trailing escape character has nothing to escape; keep it as is.
+            (true, None) => character,
+            (false, _) => character,
+        };
+
+        if character == '\n' || character == '\r' {
+            // Treat a "\r\n" or "\n\r" pair as a single linebreak.
+            let pair = if character == '\n' { '\r' } else { '\n' };
+            if characters.peek() == Some(&pair) {
+                characters.next();
+            }
+
+            if !escaped {
+                decoded.push_str(hard_linebreak);
+            }
+        } else if character.is_whitespace() {
+            decoded.push(' ');
+        } else {
+            decoded.push(character);
+        }
+    }
+
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_simple_text, decode_text, escape, split_compose};
+    use test_case::test_case;
+
+    #[test_case(r"Primiview:3.1", Some(("Primiview", "3.1")) ; "Plain")]
+    #[test_case(r"a\:b:c", Some((r"a\:b", "c")) ; "Escaped colon")]
+    #[test_case(r"a\\:b", Some((r"a\\", "b")) ; "Escaped escape before colon")]
+    #[test_case(r"dc:1:2", Some(("dc", "1:2")) ; "First colon only")]
+    #[test_case(r"a\:b", None ; "Only escaped colon")]
+    fn can_split_compose(raw: &str, expected: Option<(&str, &str)>) {
+        assert_eq!(split_compose(raw), expected);
+    }
+
+    #[test_case(r"erased in FF[3\] way", "erased in FF[3] way" ; "Escaped bracket")]
+    #[test_case(r"back\\slash", r"back\slash" ; "Escaped escape")]
+    #[test_case(r"\a\b\:", "ab:" ; "Escaped ordinary chars")]
+    #[test_case("tab\there", "tab here" ; "Tab")]
+    #[test_case("hard\nbreak", "hard\nbreak" ; "Hard linebreak")]
+    #[test_case("o\\\nk", "ok" ; "Soft linebreak")]
+    #[test_case("o\\\n\rk", "ok" ; "Soft linebreak lf cr")]
+    #[test_case("o\\\r\nk", "ok" ; "Soft linebreak cr lf")]
+    #[test_case("o\\\rk", "ok" ; "Soft linebreak cr")]
+    #[test_case("a\r\nb\rc\n\rd", "a\nb\nc\nd" ; "Linebreak styles")]
+    #[test_case("a\\\tb", "a b" ; "Escaped whitespace")]
+    fn can_decode_text(raw: &str, expected: &str) {
+        assert_eq!(decode_text(raw), expected);
+    }
+
+    #[test_case("hard\nbreak", "hard break" ; "Hard linebreak")]
+    #[test_case("o\\\nk", "ok" ; "Soft linebreak")]
+    #[test_case("a\r\nb", "a b" ; "Windows linebreak")]
+    #[test_case("tab\there", "tab here" ; "Tab")]
+    #[test_case(r"W\]", "W]" ; "Escaped bracket")]
+    fn can_decode_simple_text(raw: &str, expected: &str) {
+        assert_eq!(decode_simple_text(raw), expected);
+    }
+
+    #[test_case("FF[3] way", false, r"FF[3\] way" ; "Bracket")]
+    #[test_case(r"a\b", false, r"a\\b" ; "Escape")]
+    #[test_case("a:b", false, "a:b" ; "Colon outside compose")]
+    #[test_case("a:b", true, r"a\:b" ; "Colon in compose")]
+    fn can_escape(text: &str, in_compose: bool, expected: &str) {
+        assert_eq!(escape(text, in_compose), expected);
+    }
+
+    #[test]
+    fn escape_round_trips() {
+        let text = "Label: [a]\\b\nline";
+
+        assert_eq!(decode_text(&escape(text, false)), text);
+        assert_eq!(split_compose(&escape(text, true)), None);
+    }
+}
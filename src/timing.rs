@@ -0,0 +1,191 @@
+// From the SGF spec (https://www.red-bean.com/sgf/properties.html#TM):
+// TM gives the time limits of the game, in seconds. OT describes the method used for overtime
+// (byo-yomi); its format is free, but common ones are e.g. "5x30 byo-yomi" (five periods of 30
+// seconds) and "25/600 Canadian" (25 stones in 600 seconds).
+
+// BL and WL give the time left for a player after the move was made, in seconds.
+// OB and OW give the number of moves left (after the move of the node was played) to play in the
+// given time, i.e. stones in Canadian overtime or periods in byo-yomi.
+
+use crate::game_tree::GameTree;
+use crate::node::Node;
+use crate::property::{Color, PropertyValue};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Overtime {
+    // A number of periods that are each reset when a move is made in time.
+    ByoYomi { periods: u32, seconds: f64 },
+    // A number of stones to play in a block of time.
+    Canadian { stones: u32, seconds: f64 },
+    // A time increment added after every move.
+    Fischer { increment: f64 },
+    // Any other description, kept as it is.
+    Other(String),
+}
+
+impl Overtime {
+    // Never fails; descriptions that aren't recognised are kept as `Other`.
+    pub fn parse(val: &str) -> Self {
+        let lower = val.trim().to_ascii_lowercase();
+        let words: Vec<&str> = lower.split_whitespace().collect();
+
+        let overtime = match words.as_slice() {
+            [periods, "byo-yomi" | "byoyomi" | "byo"] => periods
+                .split_once('x')
+                .and_then(|(periods, seconds)| Some((periods.parse().ok()?, seconds_of(seconds)?)))
+                .map(|(periods, seconds)| Overtime::ByoYomi { periods, seconds }),
+            [stones, "canadian"] => stones
+                .split_once('/')
+                .and_then(|(stones, seconds)| Some((stones.parse().ok()?, seconds_of(seconds)?)))
+                .map(|(stones, seconds)| Overtime::Canadian { stones, seconds }),
+            ["fischer", increment] | [increment, "fischer"] => {
+                seconds_of(increment).map(|increment| Overtime::Fischer { increment })
+            }
+            _ => None,
+        };
+
+        overtime.unwrap_or_else(|| Overtime::Other(String::from(val)))
+    }
+
+    // The canonical description, e.g. "5x30 byo-yomi".
+    pub fn to_sgf(&self) -> String {
+        match self {
+            Overtime::ByoYomi { periods, seconds } => format!("{}x{} byo-yomi", periods, seconds),
+            Overtime::Canadian { stones, seconds } => format!("{}/{} Canadian", stones, seconds),
+            Overtime::Fischer { increment } => format!("Fischer {}", increment),
+            Overtime::Other(val) => val.clone(),
+        }
+    }
+}
+
+// The time limits of a game, from the game-info properties TM and OT.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeControl {
+    // Main time in seconds.
+    pub main_time: Option<f64>,
+    pub overtime: Option<Overtime>,
+}
+
+impl TimeControl {
+    // The time limits of the game the node at `path` belongs to.
+    pub fn of(tree: &GameTree, path: &[usize]) -> Self {
+        let value = |id| {
+            tree.game_info(path, id)
+                .and_then(|prop| prop.values.first())
+        };
+
+        TimeControl {
+            main_time: match value("TM") {
+                Some(PropertyValue::Real(seconds)) => Some(*seconds),
+                _ => None,
+            },
+            overtime: match value("OT") {
+                Some(PropertyValue::Overtime(overtime)) => Some(overtime.clone()),
+                _ => None,
+            },
+        }
+    }
+}
+
+// A player's clock after the move of a node, from BL/WL and OB/OW.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Clock {
+    // Seconds left.
+    pub time_left: Option<f64>,
+    // Stones or periods left in overtime.
+    pub overtime_left: Option<u32>,
+}
+
+impl Clock {
+    pub fn of(node: &Node, color: Color) -> Self {
+        let (time_id, overtime_id) = match color {
+            Color::Black => ("BL", "OB"),
+            Color::White => ("WL", "OW"),
+        };
+
+        Clock {
+            time_left: match node.get(time_id).and_then(|prop| prop.values.first()) {
+                Some(PropertyValue::Real(seconds)) => Some(*seconds),
+                _ => None,
+            },
+            overtime_left: match node.get(overtime_id).and_then(|prop| prop.values.first()) {
+                Some(PropertyValue::Number(left, _, _)) => Some(*left),
+                _ => None,
+            },
+        }
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.time_left.is_some() || self.overtime_left.is_some()
+    }
+}
+
+// Parses a number of seconds, allowing a trailing "s", e.g. "30" or "30s".
+fn seconds_of(val: &str) -> Option<f64> {
+    let val = val.strip_suffix('s').unwrap_or(val);
+    if val.is_empty() || !val.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        return None;
+    }
+
+    val.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Clock, Overtime, TimeControl};
+    use crate::property::Color;
+    use test_case::test_case;
+
+    #[test_case("5x30 byo-yomi", Overtime::ByoYomi { periods: 5, seconds: 30.0 } ; "Byo yomi")]
+    #[test_case("3x10s Byoyomi", Overtime::ByoYomi { periods: 3, seconds: 10.0 } ; "Byo yomi spelling")]
+    #[test_case("25/600 Canadian", Overtime::Canadian { stones: 25, seconds: 600.0 } ; "Canadian")]
+    #[test_case("Fischer 10", Overtime::Fischer { increment: 10.0 } ; "Fischer")]
+    #[test_case("15s fischer", Overtime::Fischer { increment: 15.0 } ; "Fischer increment first")]
+    #[test_case("5x30", Overtime::Other(String::from("5x30")) ; "Missing method")]
+    #[test_case("2 hours sudden death", Overtime::Other(String::from("2 hours sudden death")) ; "Other")]
+    fn can_parse_overtime(val: &str, expected: Overtime) {
+        assert_eq!(Overtime::parse(val), expected);
+    }
+
+    #[test_case("5x30 byo-yomi" ; "Byo yomi")]
+    #[test_case("25/600 Canadian" ; "Canadian")]
+    #[test_case("Fischer 2.5" ; "Fischer")]
+    #[test_case("Ing" ; "Other")]
+    fn overtime_round_trips(val: &str) {
+        assert_eq!(Overtime::parse(val).to_sgf(), val);
+    }
+
+    #[test]
+    fn can_read_time_control() {
+        let collection = crate::parse_str("(;TM[3600]OT[25/600 Canadian];B[aa])").unwrap();
+        let control = TimeControl::of(&collection.game_trees()[0], &[0]);
+
+        assert_eq!(control.main_time, Some(3600.0));
+        assert_eq!(
+            control.overtime,
+            Some(Overtime::Canadian {
+                stones: 25,
+                seconds: 600.0
+            })
+        );
+    }
+
+    #[test]
+    fn can_read_clocks() {
+        let collection = crate::parse_file("resources/example.sgf").unwrap();
+        let tree = &collection.game_trees()[0];
+
+        // B[rq]BL[105.6]OB[10]
+        let node = tree.node_at(&[4, 0, 0]).unwrap();
+        let black = Clock::of(node, Color::Black);
+        assert_eq!(black.time_left, Some(105.6));
+        assert_eq!(black.overtime_left, Some(10));
+        assert!(!Clock::of(node, Color::White).is_set());
+
+        // W[qs]WL[13.20]OW[1]
+        let node = tree.node_at(&[4, 0, 0, 0, 0, 0]).unwrap();
+        let white = Clock::of(node, Color::White);
+        assert_eq!(white.time_left, Some(13.2));
+        assert_eq!(white.overtime_left, Some(1));
+    }
+}
@@ -0,0 +1,240 @@
+// Converts games written in FF[1] to FF[3] to FF[4]:
+// - L labels become LB labels, lettered "a", "b", "c" and so on in order.
+// - M marks become MA marks.
+// - OM and OP become an OT of "OM/OP Canadian", unless the node already has an OT.
+// - LB labels become simple text, i.e. their linebreaks become spaces.
+// - Any other property FF[4] dropped is kept as a private property, with its value as written.
+// Passes written as "tt" need no conversion, as they are decoded as `Move::Pass` and written as
+// "[]". Games that are FF[4] already are left untouched.
+
+use crate::catalogue;
+use crate::codec::{Codecs, GameCodec};
+use crate::game_tree::GameTree;
+use crate::node::Node;
+use crate::point::BoardSize;
+use crate::property::{Property, PropertyValue};
+use crate::timing::Overtime;
+
+pub fn upgrade(tree: &mut GameTree) {
+    if tree.version() >= 4 {
+        return;
+    }
+
+    let (codec, size) = tree.codec(&Codecs::default());
+    let mut nodes = tree.nodes_mut();
+    for node in &mut nodes {
+        upgrade_node(node, codec.as_ref(), size);
+    }
+
+    let root = &mut nodes[0];
+    let version = vec![PropertyValue::Number(4, 1, 4)];
+    match root.properties.iter_mut().find(|prop| prop.id == "FF") {
+        Some(prop) => prop.values = version,
+        None => root.properties.insert(
+            0,
+            Property {
+                id: String::from("FF"),
+                values: version,
+            },
+        ),
+    }
+}
+
+fn upgrade_node(node: &mut Node, codec: &dyn GameCodec, size: BoardSize) {
+    let overtime = match (node.get("OM"), node.get("OP"), node.get("OT")) {
+        (Some(stones), Some(seconds), None) => {
+            match (stones.values.first(), seconds.values.first()) {
+                (Some(PropertyValue::Number(stones, _, _)), Some(PropertyValue::Real(seconds))) => {
+                    Some(Overtime::Canadian {
+                        stones: *stones,
+                        seconds: *seconds,
+                    })
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+
+    let mut properties: Vec<Property> = vec![];
+    for property in std::mem::take(&mut node.properties) {
+        match property.id.as_str() {
+            "L" => {
+                let labels = property
+                    .values
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, point)| {
+                        PropertyValue::Compose(Box::new(point), Box::new(label(index)))
+                    })
+                    .collect();
+                add(&mut properties, "LB", labels);
+            }
+            "M" => add(&mut properties, "MA", property.values),
+            "LB" => {
+                let labels = property.values.into_iter().map(simple_label).collect();
+                add(&mut properties, "LB", labels);
+            }
+            "OM" | "OP" if overtime.is_some() => {}
+            id if catalogue::definition(id).is_none() => properties.push(Property {
+                values: property
+                    .values
+                    .iter()
+                    .map(|value| PropertyValue::Unknown(value.to_sgf_with(codec, size)))
+                    .collect(),
+                ..property
+            }),
+            _ => add(&mut properties, &property.id.clone(), property.values),
+        }
+    }
+
+    if let Some(overtime) = overtime {
+        properties.push(Property {
+            id: String::from("OT"),
+            values: vec![PropertyValue::Overtime(overtime)],
+        });
+    }
+
+    node.properties = properties;
+}
+
+// Appends the values to the property with the given identifier, adding it if it's not there.
+fn add(properties: &mut Vec<Property>, id: &str, values: Vec<PropertyValue>) {
+    match properties.iter_mut().find(|prop| prop.id == id) {
+        Some(prop) => prop.values.extend(values),
+        None => properties.push(Property {
+            id: String::from(id),
+            values,
+        }),
+    }
+}
+
+// "a" to "z", then numbers from 27 on.
+fn label(index: usize) -> PropertyValue {
+    let label = match index {
+        0..=25 => ((b'a' + index as u8) as char).to_string(),
+        _ => (index + 1).to_string(),
+    };
+
+    PropertyValue::SimpleText(label)
+}
+
+fn simple_label(value: PropertyValue) -> PropertyValue {
+    match value {
+        PropertyValue::Compose(point, text) => match *text {
+            PropertyValue::Text(text) => {
+                let text = text
+                    .chars()
+                    .map(|c| if c.is_whitespace() { ' ' } else { c })
+                    .collect();
+
+                PropertyValue::Compose(point, Box::new(PropertyValue::SimpleText(text)))
+            }
+            text => PropertyValue::Compose(point, Box::new(text)),
+        },
+        value => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::point::Point;
+    use crate::property::PropertyValue;
+    use crate::timing::Overtime;
+    use crate::writer::WriteOptions;
+    use test_case::test_case;
+
+    fn upgraded(content: &str) -> String {
+        let mut collection = crate::parse_str(content).unwrap();
+        collection.upgrade();
+
+        let options = WriteOptions {
+            line_width: None,
+            ..WriteOptions::default()
+        };
+        collection.to_sgf(&options).trim_end().to_string()
+    }
+
+    #[test_case("(;FF[3];B[tt])", "(;FF[4];B[])" ; "Pass")]
+    #[test_case("(;FF[1];L[aa][bb])", "(;FF[4];LB[aa:a][bb:b])" ; "Labels")]
+    #[test_case("(;FF[3];M[aa]MA[bb])", "(;FF[4];MA[aa][bb])" ; "Marks")]
+    #[test_case("(;FF[3]LB[aa:two\nlines])", "(;FF[4]LB[aa:two lines])" ; "Label text")]
+    #[test_case("(;OM[25]OP[600])", "(;FF[4]OT[25/600 Canadian])" ; "Overtime")]
+    #[test_case("(;FF[1]BS[2]ID[Honinbo 1];EL[-3])", "(;FF[4]BS[2]ID[Honinbo 1];EL[-3])" ; "Dropped properties")]
+    #[test_case("(;FF[4]C[new])", "(;FF[4]C[new])" ; "Already FF4")]
+    fn can_upgrade(content: &str, expected: &str) {
+        assert_eq!(upgraded(content), expected);
+    }
+
+    #[test]
+    fn legacy_properties_are_only_known_before_ff4() {
+        let old = crate::parse_str("(;FF[3]SC[aa][bb])").unwrap();
+        let new = crate::parse_str("(;FF[4]SC[aa][bb])").unwrap();
+
+        assert!(old.warnings().is_empty());
+        assert_eq!(
+            old.game_trees()[0].root().get("SC").unwrap().values,
+            vec![
+                PropertyValue::Point(Point::new(0, 0)),
+                PropertyValue::Point(Point::new(1, 1))
+            ]
+        );
+        assert_eq!(new.warnings().len(), 1);
+    }
+
+    #[test]
+    fn can_read_ff3_file() {
+        let mut collection = crate::parse_file("resources/ff3.sgf").unwrap();
+        let tree = &collection.game_trees()[0];
+
+        assert_eq!(tree.version(), 3);
+        assert!(collection.warnings().is_empty());
+        assert_eq!(
+            tree.root().get("PB").unwrap().values,
+            vec![PropertyValue::SimpleText(String::from("Honinbo Shusaku"))]
+        );
+        assert_eq!(tree.main_line().count(), 9);
+
+        collection.upgrade();
+        let sgf = collection.to_sgf(&WriteOptions::default());
+        assert!(sgf.starts_with("(;FF[4]GM[1]SZ[19]EV[Castle Game]"));
+        assert!(sgf.contains(";B[pe]LB[qc:a][pc:b];W[])"));
+    }
+
+    #[test]
+    fn errors_report_identifier_without_lowercase() {
+        let error = crate::parse_str("(;FF[3];Black)").unwrap_err();
+
+        assert_eq!(error.property(), Some("B"));
+    }
+
+    #[test]
+    fn ff3_labels_are_text() {
+        let collection = crate::parse_str("(;FF[3]LB[aa:two\\\nlines])").unwrap();
+        let label = &collection.game_trees()[0].root().get("LB").unwrap().values[0];
+
+        assert_eq!(
+            *label,
+            PropertyValue::Compose(
+                Box::new(PropertyValue::Point(Point::new(0, 0))),
+                Box::new(PropertyValue::Text(String::from("twolines")))
+            )
+        );
+    }
+
+    #[test]
+    fn upgraded_overtime_is_decoded() {
+        let mut collection = crate::parse_str("(;FF[1]OM[10]OP[300])").unwrap();
+        collection.upgrade();
+
+        let root = collection.game_trees()[0].root();
+        assert_eq!(
+            root.get("OT").unwrap().values,
+            vec![PropertyValue::Overtime(Overtime::Canadian {
+                stones: 10,
+                seconds: 300.0
+            })]
+        );
+        assert!(root.get("OM").is_none());
+    }
+}
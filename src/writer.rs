@@ -0,0 +1,291 @@
+// Writes Collections back out as SGF text. Games keep the FF version they were read in, see
+// `upgrade` to convert older ones to FF[4].
+
+// White space (space, tab, carriage return, line feed, vertical tab and so on) may appear
+// anywhere between PropValues, Properties, Nodes, Sequences and GameTrees, so lines are only ever
+// broken there. A property identifier is kept on the same line as its first value.
+
+use crate::codec::{Codecs, GameCodec, Go};
+use crate::collection::Collection;
+use crate::game_tree::GameTree;
+use crate::node::Node;
+use crate::point::BoardSize;
+use crate::property::{Property, PropertyValue};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriteOptions {
+    // Lines are broken before they grow longer than this many chars, where possible.
+    // `None` writes every GameTree on a single line.
+    pub line_width: Option<usize>,
+    // Rewrites lists of points, e.g. AB or TR, into compressed rectangles.
+    pub compress_point_lists: bool,
+    // The codecs points, moves and stones are encoded with, chosen by GM. Games without one
+    // are written as in Go.
+    pub codecs: Codecs,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        WriteOptions {
+            line_width: Some(80),
+            compress_point_lists: false,
+            codecs: Codecs::default(),
+        }
+    }
+}
+
+pub struct Writer<'a> {
+    options: &'a WriteOptions,
+    output: String,
+    line_length: usize,
+    // The codec and board size of the game being written.
+    game: Option<(Arc<dyn GameCodec>, BoardSize)>,
+}
+
+impl<'a> Writer<'a> {
+    pub fn new(options: &'a WriteOptions) -> Self {
+        Writer {
+            options,
+            output: String::new(),
+            line_length: 0,
+            game: None,
+        }
+    }
+
+    pub fn finish(self) -> String {
+        self.output
+    }
+
+    pub fn write_collection(&mut self, collection: &Collection) {
+        for game_tree in collection.game_trees() {
+            self.write_game_tree(game_tree);
+            self.newline();
+        }
+    }
+
+    pub fn write_game_tree(&mut self, game_tree: &GameTree) {
+        self.game = Some(game_tree.codec(&self.options.codecs));
+        self.write_variation(game_tree);
+        self.game = None;
+    }
+
+    fn write_variation(&mut self, game_tree: &GameTree) {
+        self.push("(");
+
+        for node in game_tree.sequence() {
+            self.write_node(node);
+        }
+
+        for leaf in game_tree.leaves() {
+            // Every variation starts on a new line, which keeps deep trees readable.
+            if self.options.line_width.is_some() {
+                self.newline();
+            }
+            self.write_variation(leaf);
+        }
+
+        self.push(")");
+    }
+
+    pub fn write_node(&mut self, node: &Node) {
+        self.push(";");
+
+        for property in &node.properties {
+            if self.options.compress_point_lists && property.is_point_list() {
+                let mut compressed = property.clone();
+                if compressed.compress().is_ok() {
+                    self.write_property(&compressed);
+                    continue;
+                }
+            }
+
+            self.write_property(property);
+        }
+    }
+
+    pub fn write_property(&mut self, property: &Property) {
+        let values: Vec<String> = property
+            .values
+            .iter()
+            .map(|value| format!("[{}]", self.encode(value)))
+            .collect();
+        let mut values = values.into_iter();
+
+        let first = values.next().unwrap_or_else(|| String::from("[]"));
+        self.push(&format!("{}{}", property.id, first));
+
+        for value in values {
+            self.push(&value);
+        }
+    }
+
+    fn encode(&self, value: &PropertyValue) -> String {
+        match &self.game {
+            Some((codec, size)) => value.to_sgf_with(codec.as_ref(), *size),
+            // Outside a GameTree, e.g. from `write_node`, the game isn't known.
+            None => value.to_sgf_with(&Go, BoardSize::default()),
+        }
+    }
+
+    // Appends a chunk that can't be broken up, starting a new line first if it wouldn't fit.
+    fn push(&mut self, chunk: &str) {
+        let width = chunk.lines().next().unwrap_or_default().chars().count();
+
+        if let Some(line_width) = self.options.line_width {
+            if self.line_length > 0 && self.line_length + width > line_width {
+                self.newline();
+            }
+        }
+
+        self.output.push_str(chunk);
+        self.line_length = match chunk.rfind('\n') {
+            // Text values may contain hard linebreaks.
+            Some(index) => chunk[index + 1..].chars().count(),
+            None => self.line_length + width,
+        };
+    }
+
+    fn newline(&mut self) {
+        if self.line_length > 0 {
+            self.output.push('\n');
+            self.line_length = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WriteOptions;
+    use crate::collection::Collection;
+
+    #[test]
+    fn can_write_single_line() {
+        let content = "(;FF[4]GM[1]SZ[19];B[pd];W[dp](;B[pp])(;B[dd]))";
+        let collection = Collection::new(content).unwrap();
+
+        let options = WriteOptions {
+            line_width: None,
+            ..WriteOptions::default()
+        };
+
+        assert_eq!(collection.to_sgf(&options), format!("{}\n", content));
+    }
+
+    #[test]
+    fn variations_start_on_new_lines() {
+        let collection = Collection::new("(;FF[4];B[pd](;W[pp])(;W[dd]))(;FF[4])").unwrap();
+
+        assert_eq!(
+            collection.to_string(),
+            "(;FF[4];B[pd]\n(;W[pp])\n(;W[dd]))\n(;FF[4])\n"
+        );
+    }
+
+    #[test]
+    fn long_lines_are_wrapped_between_values() {
+        let collection = Collection::new("(;AB[aa][bb][cc][dd][ee]AW[ff][gg])").unwrap();
+
+        let options = WriteOptions {
+            line_width: Some(16),
+            ..WriteOptions::default()
+        };
+
+        assert_eq!(
+            collection.to_sgf(&options),
+            "(;AB[aa][bb][cc]\n[dd][ee]AW[ff]\n[gg])\n"
+        );
+    }
+
+    #[test]
+    fn long_values_are_not_split() {
+        let collection = Collection::new("(;C[a comment longer than the line];B[aa])").unwrap();
+
+        let options = WriteOptions {
+            line_width: Some(10),
+            ..WriteOptions::default()
+        };
+
+        assert_eq!(
+            collection.to_sgf(&options),
+            "(;\nC[a comment longer than the line]\n;B[aa])\n"
+        );
+    }
+
+    #[test]
+    fn line_length_restarts_after_hard_linebreak() {
+        let collection = Collection::new("(;C[first line\nab];B[aa])").unwrap();
+
+        let options = WriteOptions {
+            line_width: Some(10),
+            ..WriteOptions::default()
+        };
+
+        assert_eq!(
+            collection.to_sgf(&options),
+            "(;\nC[first line\nab];B[aa])\n"
+        );
+    }
+
+    #[test]
+    fn can_compress_point_lists() {
+        let collection = Collection::new("(;AB[dd][de][df][ed][ee][ef][qq]AR[aa:bb])").unwrap();
+
+        let options = WriteOptions {
+            compress_point_lists: true,
+            ..WriteOptions::default()
+        };
+
+        assert_eq!(collection.to_sgf(&options), "(;AB[dd:ef][qq]AR[aa:bb])\n");
+    }
+
+    #[test]
+    fn round_trip_is_lossless() {
+        let collection = crate::parse_file("resources/example.sgf").unwrap();
+
+        for options in [
+            WriteOptions::default(),
+            WriteOptions {
+                line_width: None,
+                compress_point_lists: false,
+                ..WriteOptions::default()
+            },
+            WriteOptions {
+                line_width: Some(1),
+                compress_point_lists: false,
+                ..WriteOptions::default()
+            },
+        ] {
+            let written = collection.to_sgf(&options);
+            let reparsed = Collection::new(&written).unwrap();
+
+            assert_eq!(reparsed.game_trees(), collection.game_trees());
+            assert_eq!(reparsed.to_sgf(&options), written);
+        }
+    }
+
+    #[test]
+    fn round_trip_keeps_compressed_point_sets() {
+        let collection = crate::parse_file("resources/example.sgf").unwrap();
+
+        let options = WriteOptions {
+            compress_point_lists: true,
+            ..WriteOptions::default()
+        };
+        let reparsed = Collection::new(&collection.to_sgf(&options)).unwrap();
+
+        let setup = |collection: &Collection| {
+            let node = &collection.game_trees()[0].leaves()[2].sequence()[0];
+            node.properties
+                .iter()
+                .map(|property| {
+                    let mut points = property.points();
+                    points.sort();
+                    points
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(setup(&reparsed), setup(&collection));
+    }
+}